@@ -8,6 +8,7 @@ fn main() -> Result<()> {
     let mut stdout = std::io::stdout();
     let options = PromptOptions::new().multiline(MultiLine {
         repeat_prompt: true,
+        ..Default::default()
     });
     let value = prompt("multiline text> ", &mut stdout, &options)?;
     println!("value: {}", value);
@@ -0,0 +1,408 @@
+//! Support for completing the word before the cursor.
+use crossterm::style::{Attribute, SetAttribute};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+#[cfg(any(feature = "async", doc))]
+use std::sync::mpsc::Receiver;
+
+/// Candidate value for a completion.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Completion {
+    /// Value to insert.
+    pub value: String,
+    /// Description displayed dimmed beside the value
+    /// when the candidates are listed.
+    pub description: Option<String>,
+}
+
+impl Completion {
+    /// Set the description for the candidate.
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+impl From<&str> for Completion {
+    fn from(value: &str) -> Self {
+        Self::from(value.to_string())
+    }
+}
+
+impl From<String> for Completion {
+    fn from(value: String) -> Self {
+        Self {
+            value,
+            description: None,
+        }
+    }
+}
+
+/// Trait for completion implementations.
+pub trait Completer {
+    /// Get the completion candidates for a line.
+    ///
+    /// The position is the byte offset of the cursor in the line,
+    /// the returned offset is the start of the text the candidates
+    /// replace; the replaced text ends at the cursor.
+    fn complete(&self, line: &str, position: usize)
+        -> (usize, Vec<Completion>);
+}
+
+/// Receives the start offset and candidates for a completion
+/// when they are ready.
+#[cfg(any(feature = "async", doc))]
+#[doc(cfg(feature = "async"))]
+pub type PendingCompletion = Receiver<(usize, Vec<Completion>)>;
+
+/// Trait for completion implementations that compute the
+/// candidates in the background.
+///
+/// A spinner is displayed below the prompt until the candidates
+/// are sent, candidates that arrive after the buffer was changed
+/// are discarded.
+#[cfg(any(feature = "async", doc))]
+#[doc(cfg(feature = "async"))]
+pub trait AsyncCompleter {
+    /// Start computing the completion candidates for a line.
+    ///
+    /// See [Completer::complete] for the meaning of the
+    /// position and the start offset sent with the candidates.
+    fn complete(&self, line: &str, position: usize) -> PendingCompletion;
+}
+
+/// Frames of the spinner displayed while waiting for candidates.
+#[cfg(feature = "async")]
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// Line displayed while waiting for candidates.
+#[cfg(feature = "async")]
+pub(crate) fn spinner(frame: usize) -> String {
+    format!("{} completing", SPINNER[frame % SPINNER.len()])
+}
+
+/// Completes the word before the cursor from a list of words.
+#[derive(Default)]
+pub struct WordCompleter {
+    words: Vec<String>,
+    ignore_case: bool,
+}
+
+impl WordCompleter {
+    /// Create a new word completer.
+    pub fn new(words: Vec<String>) -> Self {
+        Self {
+            words,
+            ignore_case: false,
+        }
+    }
+
+    /// Match the words ignoring case.
+    pub fn ignore_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+}
+
+impl Completer for WordCompleter {
+    fn complete(
+        &self,
+        line: &str,
+        position: usize,
+    ) -> (usize, Vec<Completion>) {
+        let before = &line[..position];
+        let start = before
+            .rfind(char::is_whitespace)
+            .map(|i| i + before[i..].chars().next().unwrap().len_utf8())
+            .unwrap_or(0);
+        let word = &before[start..];
+        let candidates = if word.is_empty() {
+            vec![]
+        } else {
+            self.words
+                .iter()
+                .filter(|w| {
+                    if self.ignore_case {
+                        w.to_lowercase().starts_with(&word.to_lowercase())
+                    } else {
+                        w.starts_with(word)
+                    }
+                })
+                .map(|w| Completion::from(&w[..]))
+                .collect()
+        };
+        (start, candidates)
+    }
+}
+
+/// Candidates listed below the prompt after an ambiguous completion.
+pub(crate) struct Listing {
+    /// Byte offset where the completed text starts.
+    pub start: usize,
+    /// Candidates for the completion.
+    pub candidates: Vec<Completion>,
+    /// Index of the candidate inserted into the buffer.
+    pub selected: Option<usize>,
+}
+
+impl Listing {
+    /// Create a listing of candidates.
+    pub fn new(start: usize, candidates: Vec<Completion>) -> Self {
+        Self {
+            start,
+            candidates,
+            selected: None,
+        }
+    }
+
+    /// Select the next candidate wrapping to the first candidate.
+    pub fn select_next(&mut self) -> &str {
+        let next = self.selected.map(|i| i + 1).unwrap_or(0);
+        let index = next % self.candidates.len();
+        self.selected = Some(index);
+        &self.candidates[index].value
+    }
+}
+
+/// Layout of candidates in columns like the bash completion list.
+///
+/// Candidates fill each column from top to bottom and every
+/// column has the width of the longest candidate; candidates
+/// with descriptions are listed one per row.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct Columns {
+    /// Number of rows.
+    pub rows: usize,
+    /// Number of columns.
+    pub columns: usize,
+    /// Width of each cell including the selection marker.
+    pub cell: usize,
+    /// Columns available for descriptions, zero when no candidate
+    /// has a description.
+    pub description: usize,
+}
+
+/// Columns between each column of candidates.
+const GAP: usize = 2;
+
+impl Columns {
+    /// Compute the columns that fit candidates in a width.
+    ///
+    /// A width of zero does not limit the number of columns
+    /// and a zero maximum allows as many columns as fit.
+    pub fn new(
+        candidates: &[Completion],
+        width: usize,
+        maximum: usize,
+    ) -> Self {
+        let longest = candidates
+            .iter()
+            .map(|c| UnicodeWidthStr::width(&c.value[..]))
+            .max()
+            .unwrap_or(0);
+        // Every cell is prefixed with a marker and a space
+        let cell = longest + 2;
+        if candidates.iter().any(|c| c.description.is_some()) {
+            let description = if width == 0 {
+                usize::MAX
+            } else {
+                width.saturating_sub(cell + GAP)
+            };
+            return Self {
+                rows: candidates.len(),
+                columns: 1,
+                cell,
+                description,
+            };
+        }
+        let fit = if width == 0 {
+            candidates.len()
+        } else {
+            (width + GAP) / (cell + GAP)
+        };
+        let mut columns = fit.clamp(1, candidates.len().max(1));
+        if maximum > 0 {
+            columns = columns.min(maximum);
+        }
+        let rows = candidates.len().div_ceil(columns);
+        // Fewer columns may be needed for the number of rows
+        if rows > 0 {
+            columns = candidates.len().div_ceil(rows);
+        }
+        Self {
+            rows,
+            columns,
+            cell,
+            description: 0,
+        }
+    }
+
+    /// Index of the candidate displayed at a row and column.
+    pub fn index(&self, row: usize, column: usize) -> usize {
+        column * self.rows + row
+    }
+
+    /// Lines for the candidates with the selection marked.
+    pub fn lines(
+        &self,
+        candidates: &[Completion],
+        selected: Option<usize>,
+    ) -> Vec<String> {
+        (0..self.rows)
+            .map(|row| {
+                let mut line = String::new();
+                for column in 0..self.columns {
+                    let index = self.index(row, column);
+                    if let Some(candidate) = candidates.get(index) {
+                        if column > 0 {
+                            let used = UnicodeWidthStr::width(&line[..]);
+                            let pad = column * (self.cell + GAP) - used;
+                            line.push_str(&" ".repeat(pad));
+                        }
+                        let marker =
+                            if selected == Some(index) { '>' } else { ' ' };
+                        line.push(marker);
+                        line.push(' ');
+                        line.push_str(&candidate.value);
+                        if let Some(description) = &candidate.description {
+                            self.push_description(&mut line, description);
+                        }
+                    }
+                }
+                line
+            })
+            .collect()
+    }
+
+    /// Append a dimmed description truncated to the columns
+    /// available for descriptions.
+    fn push_description(&self, line: &mut String, description: &str) {
+        if self.description == 0 {
+            return;
+        }
+        let used = UnicodeWidthStr::width(&line[..]);
+        line.push_str(&" ".repeat(self.cell + GAP - used));
+        line.push_str(&SetAttribute(Attribute::Dim).to_string());
+        if UnicodeWidthStr::width(description) <= self.description {
+            line.push_str(description);
+        } else {
+            // Leave a column for the ellipsis
+            let mut cols = 0;
+            let end = UnicodeSegmentation::grapheme_indices(description, true)
+                .find(|(_, g)| {
+                    cols += UnicodeWidthStr::width(*g);
+                    cols >= self.description
+                })
+                .map(|(i, _)| i)
+                .unwrap_or(description.len());
+            line.push_str(description[..end].trim_end());
+            line.push('…');
+        }
+        line.push_str(&SetAttribute(Attribute::NormalIntensity).to_string());
+    }
+}
+
+/// Find the longest common prefix of the candidates.
+pub(crate) fn common_prefix(candidates: &[Completion]) -> &str {
+    let mut it = candidates.iter();
+    let first = match it.next() {
+        Some(first) => &first.value[..],
+        None => return "",
+    };
+    it.fold(first, |prefix, candidate| {
+        let len = prefix
+            .char_indices()
+            .zip(candidate.value.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map(|((i, c), _)| i + c.len_utf8())
+            .unwrap_or(0);
+        &prefix[..len]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete_words() {
+        let completer = WordCompleter::new(vec![
+            "status".to_string(),
+            "stash".to_string(),
+            "commit".to_string(),
+        ]);
+        let (start, candidates) = completer.complete("git sta", 7);
+        assert_eq!(4, start);
+        assert_eq!(2, candidates.len());
+        assert_eq!("sta", common_prefix(&candidates));
+
+        let (_, candidates) = completer.complete("git ", 4);
+        assert!(candidates.is_empty());
+
+        let (_, candidates) = completer.complete("STA", 3);
+        assert!(candidates.is_empty());
+        let completer = completer.ignore_case(true);
+        let (_, candidates) = completer.complete("STA", 3);
+        assert_eq!(2, candidates.len());
+    }
+
+    #[test]
+    fn completion_columns() {
+        let candidates: Vec<Completion> = (0..20)
+            .map(|i| Completion::from(format!("c{:02}", i)))
+            .collect();
+        let columns = Columns::new(&candidates, 40, 0);
+        assert_eq!(
+            Columns {
+                rows: 4,
+                columns: 5,
+                cell: 5,
+                description: 0,
+            },
+            columns
+        );
+        assert_eq!(9, columns.index(1, 2));
+
+        let lines = columns.lines(&candidates, Some(9));
+        assert_eq!(4, lines.len());
+        assert_eq!("  c01    c05  > c09    c13    c17", lines[1]);
+        assert_eq!("  c03    c07    c11    c15    c19", lines[3]);
+        assert!(lines.iter().all(|line| line.len() <= 40));
+
+        let columns = Columns::new(&candidates, 40, 2);
+        assert_eq!((10, 2), (columns.rows, columns.columns));
+        let columns = Columns::new(&candidates[..2], 0, 0);
+        assert_eq!((1, 2), (columns.rows, columns.columns));
+    }
+
+    #[test]
+    fn completion_descriptions() {
+        let candidates = vec![
+            Completion::from("status").description("Show the working tree"),
+            Completion::from("stash"),
+            Completion::from("show").description("Show objects"),
+        ];
+        let columns = Columns::new(&candidates, 24, 0);
+        assert_eq!(
+            (3, 1, 14),
+            (columns.rows, columns.columns, columns.description)
+        );
+
+        let (dim, normal) = ("\x1b[2m", "\x1b[22m");
+        let lines = columns.lines(&candidates, Some(2));
+        assert_eq!(
+            vec![
+                format!("  status  {}Show the work…{}", dim, normal),
+                "  stash".to_string(),
+                format!("> show    {}Show objects{}", dim, normal),
+            ],
+            lines
+        );
+
+        // Descriptions are omitted when there is no room
+        let lines = Columns::new(&candidates, 10, 0).lines(&candidates, None);
+        assert_eq!("  status", lines[0]);
+    }
+}
@@ -0,0 +1,2636 @@
+//! Line editor that applies key actions to a terminal buffer.
+//!
+//! The editor does not perform any terminal queries, the caller
+//! is responsible for supplying the terminal size, the starting
+//! cursor position and the key events.
+use anyhow::Result;
+use crossterm::{
+    cursor,
+    event::{KeyCode, KeyEvent, KeyModifiers},
+    terminal::{disable_raw_mode, enable_raw_mode, is_raw_mode_enabled, size},
+};
+use std::borrow::Cow;
+use std::io::Write;
+
+use crate::{
+    external,
+    kill_ring::KillRing,
+    paste,
+    terminal_buffer::{normalize_newlines, TerminalBuffer},
+    AsPrefix, Counter, EmptyAccept, EmptyBackspace, EscapeAction, Intercept,
+    KeyAction, PromptOptions, Validity,
+};
+
+#[cfg(feature = "completion")]
+use crate::completion::{
+    common_prefix, Columns, Completer, Completion, Listing, WordCompleter,
+};
+
+#[cfg(feature = "async")]
+use crate::completion::{spinner, PendingCompletion};
+
+#[cfg(feature = "async")]
+use std::sync::mpsc::TryRecvError;
+
+#[cfg(feature = "history")]
+use crate::{picker::Picker, search::Search};
+
+#[cfg(feature = "timestamp")]
+use crate::timestamp;
+#[cfg(feature = "timestamp")]
+use std::time::SystemTime;
+
+/// Whether the editor should continue reading events.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum Flow {
+    /// Keep reading events.
+    Continue,
+    /// The line was submitted by an action with an optional name.
+    Submit(Option<&'static str>),
+    /// The prompt was aborted.
+    Abort,
+    /// Backspace was pressed on an empty buffer.
+    Back,
+}
+
+/// Completion candidates computed in the background.
+#[cfg(feature = "async")]
+struct Pending {
+    receiver: PendingCompletion,
+    /// Buffer and cursor when the completion started.
+    line: String,
+    cursor: usize,
+    /// Frame of the spinner.
+    frame: usize,
+}
+
+/// Editing state for a single prompt.
+pub(crate) struct Editor<'a> {
+    buf: TerminalBuffer<'a>,
+    options: &'a PromptOptions,
+    kill_ring: KillRing,
+    /// Cursor position and raw mode state when suspended.
+    suspended: Option<((u16, u16), bool)>,
+    /// Numeric argument for the next action.
+    argument: Option<usize>,
+    /// Whether the next key is the target for deleting to a character.
+    delete_to_char: bool,
+    /// Whether escape was pressed as a meta prefix.
+    meta: bool,
+    #[cfg(feature = "history")]
+    history_buffer: String,
+    #[cfg(feature = "history")]
+    search: Option<Search>,
+    #[cfg(feature = "history")]
+    picker: Option<Picker>,
+    #[cfg(feature = "completion")]
+    listing: Option<Listing>,
+    #[cfg(feature = "async")]
+    pending: Option<Pending>,
+}
+
+impl<'a> Editor<'a> {
+    /// Create a new editor.
+    pub fn new<P: AsPrefix + ?Sized>(
+        prefix: &'a P,
+        options: &'a PromptOptions,
+    ) -> Self {
+        let echo = if let Some(password) = &options.password {
+            password.echo
+        } else {
+            None
+        };
+        let mut buf = TerminalBuffer::new(prefix, echo);
+        if let Some(password) = &options.password {
+            buf.set_hidden(password.hidden);
+        }
+        if let Some(multiline) = &options.multiline {
+            buf.set_repeat_prefix(multiline.repeat_prompt);
+        }
+        buf.set_defer_flush(options.defer_flush);
+        buf.set_theme(options.theme);
+        buf.set_border(options.border);
+        // The auto submit length is the maximum for a grapheme count
+        buf.set_counter(options.counter.map(|counter| {
+            Counter {
+                maximum: counter
+                    .maximum
+                    .or(options.auto_submit.filter(|_| !counter.columns)),
+                ..counter
+            }
+        }));
+        buf.set_wrap_marker(options.wrap_marker);
+        buf.set_stop_at_whitespace(options.stop_at_whitespace);
+        if let Some(value) = &options.read_only {
+            buf.set_value(value, value.len());
+        }
+        Self {
+            buf,
+            options,
+            kill_ring: Default::default(),
+            suspended: None,
+            argument: None,
+            delete_to_char: false,
+            meta: false,
+            #[cfg(feature = "history")]
+            history_buffer: String::new(),
+            #[cfg(feature = "history")]
+            search: None,
+            #[cfg(feature = "history")]
+            picker: None,
+            #[cfg(feature = "completion")]
+            listing: None,
+            #[cfg(feature = "async")]
+            pending: None,
+        }
+    }
+
+    /// Set the terminal size.
+    pub fn set_size(&mut self, size: (u16, u16)) {
+        self.buf.set_size(size);
+    }
+
+    /// Write the initial prompt with the cursor at the given position.
+    pub fn start<W>(
+        &mut self,
+        writer: &mut W,
+        position: (u16, u16),
+    ) -> Result<()>
+    where
+        W: Write,
+    {
+        self.buf.start(writer, position)
+    }
+
+    /// Redraw the prompt, used when the terminal is resized.
+    pub fn redraw<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.buf.redraw(writer)
+    }
+
+    /// Determine if the prompt wraps at the current size.
+    pub fn is_wrapped(&self) -> bool {
+        self.buf.is_wrapped()
+    }
+
+    /// Validate the buffer, without validation the buffer is valid.
+    fn validity(&self) -> Validity {
+        let validity = self.options.check(self.buf.buffer());
+        match &self.options.allowed {
+            Some(allowed)
+                if validity == Validity::Valid
+                    && allowed.find(self.buf.buffer()).is_none() =>
+            {
+                Validity::Invalid
+            }
+            _ => validity,
+        }
+    }
+
+    /// Get the current value.
+    pub fn value(&self) -> &str {
+        self.buf.buffer()
+    }
+
+    /// Get the prompt as it is painted without styling.
+    pub fn visible_line(&self) -> String {
+        self.buf.visible_line()
+    }
+
+    /// Insert pasted text at the cursor.
+    pub fn paste<W>(&mut self, writer: &mut W, text: &str) -> Result<Flow>
+    where
+        W: Write,
+    {
+        if self.options.read_only.is_some() {
+            return Ok(Flow::Continue);
+        }
+        let count = self.buf.grapheme_count();
+        let text = match &self.options.on_paste {
+            Some(hook) => {
+                let mut hook = hook.lock().unwrap();
+                Cow::Owned((hook)(text.to_string()))
+            }
+            None => Cow::Borrowed(text),
+        };
+        let text = paste::sanitize(
+            &text,
+            self.options.paste_whitespace,
+            self.options.multiline.is_some(),
+        );
+        self.buf.insert_lines(writer, &text)?;
+        self.after_edit(writer, count, Flow::Continue)
+    }
+
+    /// Set the mask character and redraw the prompt.
+    ///
+    /// The value is unchanged, only the displayed text is affected.
+    pub fn set_mask<W>(
+        &mut self,
+        writer: &mut W,
+        echo: Option<char>,
+    ) -> Result<()>
+    where
+        W: Write,
+    {
+        self.buf.set_echo(echo);
+        self.buf.redraw(writer)
+    }
+
+    /// Suspend the prompt so that another program may use the terminal.
+    ///
+    /// The cursor is moved below the prompt and raw mode is disabled.
+    pub fn suspend<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let position = self.buf.finish(writer)?;
+        let raw = is_raw_mode_enabled()?;
+        if raw {
+            disable_raw_mode()?;
+        }
+        self.suspended = Some((position, raw));
+        Ok(())
+    }
+
+    /// Resume a suspended prompt redrawing it below any
+    /// output written while suspended.
+    pub fn resume<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        if let Some((position, raw)) = self.suspended.take() {
+            let position = if raw {
+                enable_raw_mode()?;
+                self.buf.set_size(self.options.size.map_or_else(size, Ok)?);
+                cursor::position()?
+            } else {
+                position
+            };
+            self.buf.start(writer, position)?;
+        }
+        Ok(())
+    }
+
+    /// Handle a key event.
+    pub fn handle_key<W>(
+        &mut self,
+        writer: &mut W,
+        event: &KeyEvent,
+    ) -> Result<Flow>
+    where
+        W: Write,
+    {
+        let count = self.buf.grapheme_count();
+        let flow = self.dispatch_key(writer, event)?;
+        self.after_edit(writer, count, flow)
+    }
+
+    /// Submit the value when an edit from a value of `count`
+    /// graphemes reached the auto submit length.
+    fn after_edit<W>(
+        &mut self,
+        writer: &mut W,
+        count: usize,
+        flow: Flow,
+    ) -> Result<Flow>
+    where
+        W: Write,
+    {
+        if let (Flow::Continue, Some(length)) =
+            (&flow, self.options.auto_submit)
+        {
+            let new_count = self.buf.grapheme_count();
+            if count < length && new_count >= length {
+                return self.submit(writer, None);
+            }
+        }
+        Ok(flow)
+    }
+
+    /// Dispatch a key event to the current mode or the key bindings.
+    fn dispatch_key<W>(
+        &mut self,
+        writer: &mut W,
+        event: &KeyEvent,
+    ) -> Result<Flow>
+    where
+        W: Write,
+    {
+        // Escape as a meta prefix adds alt to the next key
+        let composed;
+        let event = if self.meta {
+            self.meta = false;
+            composed =
+                KeyEvent::new(event.code, event.modifiers | KeyModifiers::ALT);
+            &composed
+        } else {
+            event
+        };
+
+        if let Some(hook) = &self.options.on_key {
+            let mut hook = hook.lock().unwrap();
+            match (hook)(event) {
+                Intercept::Continue => {}
+                Intercept::Consume => return Ok(Flow::Continue),
+                Intercept::Override(action) => {
+                    drop(hook);
+                    return self.action(writer, action);
+                }
+            }
+        }
+
+        let is_reset = self
+            .options
+            .bindings
+            .first(event)
+            .map(|actions| actions.contains(&KeyAction::ResetLine))
+            .unwrap_or(false);
+        if is_reset {
+            return self.action(writer, KeyAction::ResetLine);
+        }
+
+        if self.delete_to_char {
+            self.delete_to_char = false;
+            if let (KeyCode::Char(c), false) = (
+                event.code,
+                event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT),
+            ) {
+                match self.buf.erase_to_char(writer, c)? {
+                    Some(erased) => self.kill_ring.push(erased),
+                    None => self.ring_bell(writer)?,
+                }
+            }
+            return Ok(Flow::Continue);
+        }
+
+        #[cfg(feature = "history")]
+        if self.search.is_some() && self.search_key(writer, event)? {
+            return Ok(Flow::Continue);
+        }
+
+        #[cfg(feature = "history")]
+        if self.picker.is_some() {
+            self.picker_key(writer, event)?;
+            return Ok(Flow::Continue);
+        }
+
+        #[cfg(feature = "completion")]
+        if self.listing.is_some() {
+            let is_tab = self
+                .options
+                .bindings
+                .first(event)
+                .map(|actions| actions.contains(&KeyAction::Tab))
+                .unwrap_or(false);
+            if is_tab {
+                self.listing_next(writer)?;
+                return Ok(Flow::Continue);
+            }
+            let listing = self.listing.take().unwrap();
+            if let Some(index) = listing.selected {
+                self.completion_accepted(&listing.candidates[index].value);
+            }
+            self.buf.set_info(Vec::new());
+            self.buf.redraw(writer)?;
+        }
+
+        let actions = self.options.bindings.first(event);
+
+        // A binding for escape takes precedence over the escape action
+        if event.code == KeyCode::Esc
+            && event.modifiers.is_empty()
+            && actions.is_none()
+        {
+            match self.options.escape {
+                EscapeAction::Ignore => {}
+                EscapeAction::Abort => {
+                    return self.action(writer, KeyAction::AbortPrompt)
+                }
+                EscapeAction::Clear => {
+                    if self.options.read_only.is_none() {
+                        self.buf.refresh(writer, "")?;
+                    }
+                }
+                EscapeAction::Meta => self.meta = true,
+            }
+            return Ok(Flow::Continue);
+        }
+
+        if let Some(actions) = actions {
+            for action in actions {
+                let flow = self.action(writer, action)?;
+                if flow != Flow::Continue {
+                    return Ok(flow);
+                }
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    /// Apply a key action.
+    fn action<W>(&mut self, writer: &mut W, action: KeyAction) -> Result<Flow>
+    where
+        W: Write,
+    {
+        let read_only = self.options.read_only.is_some();
+        if read_only && action.is_edit() {
+            return Ok(Flow::Continue);
+        }
+
+        // The argument only applies to the next action
+        let argument = match action {
+            KeyAction::DigitArgument(_) => None,
+            _ => self.argument.take(),
+        };
+
+        if let Some(predicate) = &self.options.is_action_enabled {
+            let mut predicate = predicate.lock().unwrap();
+            if !(predicate)(&action, self.buf.buffer()) {
+                drop(predicate);
+                if !self.options.silent_disabled {
+                    self.ring_bell(writer)?;
+                }
+                return Ok(Flow::Continue);
+            }
+        }
+
+        match action {
+            KeyAction::WriteChar(c) => {
+                let expanded = if c == ' ' {
+                    self.expand_abbreviation(writer)?
+                } else {
+                    None
+                };
+                if !expanded
+                    .map(|e| e.ends_with(char::is_whitespace))
+                    .unwrap_or(false)
+                {
+                    self.buf.write_char(writer, c)?;
+                }
+            }
+            KeyAction::SubmitLine => {
+                if read_only {
+                    return self.submit(writer, None);
+                }
+                self.expand_abbreviation(writer)?;
+                if self.options.multiline.is_some()
+                    || (self.options.continue_unbalanced
+                        && is_unbalanced(self.buf.buffer()))
+                    || self.validity() == Validity::Incomplete
+                {
+                    self.buf.write_char(writer, '\n')?;
+                } else {
+                    return self.submit(writer, None);
+                }
+            }
+            KeyAction::SubmitAs(name) => {
+                return self.submit(writer, Some(name));
+            }
+            KeyAction::MoveCursorLeft => {
+                self.buf.move_left(writer)?;
+            }
+            KeyAction::MoveCursorRight => {
+                self.buf.move_right(writer)?;
+            }
+            KeyAction::EraseCharacter => {
+                if self.buf.buffer().is_empty() {
+                    match self.options.empty_backspace {
+                        EmptyBackspace::Ignore => {}
+                        EmptyBackspace::Bell => self.ring_bell(writer)?,
+                        EmptyBackspace::Back => {
+                            self.buf.finish(writer)?;
+                            return Ok(Flow::Back);
+                        }
+                    }
+                } else {
+                    self.buf.erase_before(writer, 1)?;
+                }
+            }
+            KeyAction::EraseCharacterForward => {
+                self.buf.erase_after(writer, 1)?;
+            }
+            KeyAction::AbortPrompt => {
+                self.buf.finish(writer)?;
+                return Ok(Flow::Abort);
+            }
+            KeyAction::ClearScreen => {
+                self.buf.clear_screen(writer)?;
+            }
+            KeyAction::MoveToLineBegin => {
+                self.buf.move_to_line_begin(writer)?;
+            }
+            KeyAction::MoveToLineEnd => {
+                self.buf.move_to_line_end(writer)?;
+            }
+            KeyAction::ForwardWord => {
+                self.buf.move_word_forward(writer)?;
+            }
+            KeyAction::BackwardWord => {
+                self.buf.move_word_backward(writer)?;
+            }
+            KeyAction::MoveToPreviousParagraph => {
+                self.buf.move_to_previous_paragraph(writer)?;
+            }
+            KeyAction::MoveToNextParagraph => {
+                self.buf.move_to_next_paragraph(writer)?;
+            }
+            KeyAction::EraseToLineBegin => {
+                let erased = self.buf.erase_to_line_begin(writer)?;
+                self.kill_ring.push(erased);
+            }
+            KeyAction::EraseToLineEnd => {
+                let erased = self.buf.erase_to_line_end(writer)?;
+                self.kill_ring.push(erased);
+            }
+            KeyAction::ErasePreviousWord => {
+                let erased = self.buf.erase_word_before(writer)?;
+                self.kill_ring.push(erased);
+            }
+            KeyAction::UnixWordRubout => {
+                let erased = self.buf.erase_whitespace_word_before(writer)?;
+                self.kill_ring.push(erased);
+            }
+            KeyAction::DeleteToChar => {
+                self.delete_to_char = true;
+            }
+            KeyAction::TransposeForward => {
+                self.buf.transpose_forward(writer)?;
+            }
+            KeyAction::DeleteHorizontalSpace => {
+                self.buf.replace_horizontal_space(writer, "")?;
+            }
+            KeyAction::JustOneSpace => {
+                self.buf.replace_horizontal_space(writer, " ")?;
+            }
+            KeyAction::CopyWord => {
+                if let Some(range) = self.buf.word_at_cursor() {
+                    let word = self.buf.buffer()[range].to_string();
+                    self.kill_ring.push(word);
+                }
+            }
+            KeyAction::Yank => {
+                if let Some(text) = self.kill_ring.yank() {
+                    self.buf.insert(writer, text)?;
+                }
+            }
+            KeyAction::DigitArgument(digit) => {
+                let argument = self.argument.unwrap_or(0);
+                self.argument = Some(
+                    argument.saturating_mul(10).saturating_add(digit as usize),
+                );
+            }
+            KeyAction::GoToColumn => {
+                self.buf.move_to_column(writer, argument.unwrap_or(0))?;
+            }
+            KeyAction::RotateKillRing => {
+                self.kill_ring.rotate();
+            }
+            KeyAction::SetMark => {
+                self.buf.set_mark();
+            }
+            KeyAction::ToggleComment => {
+                let prefix = self.options.comment.as_deref().unwrap_or("# ");
+                self.buf.toggle_line_prefix(writer, prefix)?;
+            }
+            KeyAction::ResetLine => {
+                self.reset(writer)?;
+            }
+            KeyAction::SortLines => {
+                self.buf.sort_lines(writer)?;
+            }
+            KeyAction::DedupLines => {
+                self.buf.dedup_lines(writer)?;
+            }
+            KeyAction::ToggleMask => {
+                if let Some(password) = &self.options.password {
+                    let masked =
+                        self.buf.echo().is_some() || self.buf.is_hidden();
+                    self.buf.set_hidden(!masked && password.hidden);
+                    let echo = if masked { None } else { password.echo };
+                    self.set_mask(writer, echo)?;
+                }
+            }
+            KeyAction::SelectAll => {
+                self.buf.select_all(writer)?;
+            }
+            KeyAction::KillRegion => {
+                let erased = self.buf.erase_region(writer)?;
+                self.kill_ring.push(erased);
+            }
+            KeyAction::CopyRegion => {
+                if let Some(region) = self.buf.region() {
+                    let text = self.buf.buffer()[region].to_string();
+                    self.kill_ring.push(text);
+                }
+            }
+            KeyAction::WrapRegion(open, close) => {
+                self.buf.wrap_region(writer, open, close)?;
+            }
+            KeyAction::EditInEditor => {
+                let command = self
+                    .options
+                    .editor
+                    .clone()
+                    .unwrap_or_else(external::editor_command);
+                self.suspend(writer)?;
+                let value = external::edit(&command, self.buf.buffer());
+                self.resume(writer)?;
+                self.buf.refresh(writer, normalize_newlines(&value?))?;
+            }
+            KeyAction::Tab => {
+                let complete = self
+                    .options
+                    .tab
+                    .as_ref()
+                    .map(|t| t.complete)
+                    .unwrap_or(true);
+                if !(complete && self.complete(writer)?) {
+                    if let Some(tab) = &self.options.tab {
+                        self.buf.insert(writer, &tab.indent.whitespace())?;
+                    }
+                }
+            }
+            #[cfg(feature = "history")]
+            KeyAction::HistoryPrevious => {
+                if let Some(history) = &self.options.history {
+                    let mut history = history.lock().unwrap();
+
+                    if history.is_last() {
+                        self.history_buffer = self.buf.buffer().to_string();
+                    }
+
+                    if let Some(history_line) = history.previous() {
+                        self.buf.refresh(writer, history_line)?;
+                    }
+                }
+            }
+            #[cfg(feature = "history")]
+            KeyAction::ReverseSearch => {
+                if self.options.history.is_some() {
+                    let search = Search::new(self.buf.buffer().to_string());
+                    self.search = Some(search);
+                    self.search_render(writer)?;
+                }
+            }
+            #[cfg(feature = "history")]
+            KeyAction::HistoryToggle => {
+                if let Some(history) = &self.options.history {
+                    let history = history.lock().unwrap();
+                    let mut recent = history.items().iter().rev();
+                    let (last, previous) = (recent.next(), recent.next());
+                    let item =
+                        if last.map(|s| &s[..]) == Some(self.buf.buffer()) {
+                            previous
+                        } else {
+                            last
+                        };
+                    if let Some(item) = item {
+                        self.buf.refresh(writer, item)?;
+                    }
+                }
+            }
+            #[cfg(feature = "timestamp")]
+            KeyAction::InsertTimestamp => {
+                let format = self
+                    .options
+                    .timestamp_format
+                    .as_deref()
+                    .unwrap_or(timestamp::DEFAULT_FORMAT);
+                let now = self.options.clock.unwrap_or(SystemTime::now);
+                let text = timestamp::format_or_default(now(), format);
+                self.buf.insert(writer, &text)?;
+            }
+            #[cfg(feature = "history")]
+            KeyAction::HistoryPicker => {
+                if self.options.history.is_some() {
+                    let picker = Picker::new(self.buf.buffer().to_string());
+                    self.picker = Some(picker);
+                    self.picker_filter(writer)?;
+                }
+            }
+            #[cfg(feature = "history")]
+            KeyAction::HistoryNext => {
+                if let Some(history) = &self.options.history {
+                    let mut history = history.lock().unwrap();
+                    if let Some(history_line) = history.next() {
+                        self.buf.refresh(writer, history_line)?;
+                    } else {
+                        self.buf.refresh(writer, &self.history_buffer)?;
+                    }
+                }
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    /// Handle a key event during a reverse search.
+    ///
+    /// Returns `false` when the search was accepted and the key
+    /// event should be handled normally.
+    #[cfg(feature = "history")]
+    fn search_key<W>(
+        &mut self,
+        writer: &mut W,
+        event: &KeyEvent,
+    ) -> Result<bool>
+    where
+        W: Write,
+    {
+        let search = self.search.as_mut().unwrap();
+        let is_search = self
+            .options
+            .bindings
+            .first(event)
+            .map(|actions| actions.contains(&KeyAction::ReverseSearch))
+            .unwrap_or(false);
+
+        match event.code {
+            _ if is_search => {
+                // Search for older matches
+                let before = search.index.unwrap_or(usize::MAX);
+                self.search_find(writer, before)?;
+            }
+            KeyCode::Char(c)
+                if !event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                search.query.push(c);
+                let before = search.index.map(|i| i + 1).unwrap_or(usize::MAX);
+                self.search_find(writer, before)?;
+            }
+            KeyCode::Backspace => {
+                search.query.pop();
+                self.search_find(writer, usize::MAX)?;
+            }
+            KeyCode::Esc | KeyCode::Char('g')
+                if event.code == KeyCode::Esc
+                    || event.modifiers == KeyModifiers::CONTROL =>
+            {
+                // Cancel the search restoring the original buffer
+                let saved = std::mem::take(&mut search.saved);
+                self.search_exit();
+                self.buf.refresh(writer, saved)?;
+            }
+            _ => {
+                self.search_exit();
+                self.buf.redraw(writer)?;
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Find the query in history items before an index.
+    #[cfg(feature = "history")]
+    fn search_find<W>(&mut self, writer: &mut W, before: usize) -> Result<()>
+    where
+        W: Write,
+    {
+        let history = self.options.history.as_ref().unwrap();
+        let history = history.lock().unwrap();
+        let search = self.search.as_mut().unwrap();
+        if search.query.is_empty() {
+            search.failed = false;
+        } else if let Some((index, offset)) =
+            Search::find(history.items(), &search.query, before)
+        {
+            search.index = Some(index);
+            search.failed = false;
+            let item = &history.items()[index];
+            self.buf.set_value(item, offset);
+            self.buf
+                .set_highlight(Some(offset..offset + search.query.len()));
+        } else {
+            search.failed = true;
+        }
+        drop(history);
+
+        if self.search.as_ref().unwrap().failed {
+            self.ring_bell(writer)?;
+        }
+        self.search_render(writer)
+    }
+
+    /// Draw the search prompt.
+    #[cfg(feature = "history")]
+    fn search_render<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let search = self.search.as_ref().unwrap();
+        self.buf.set_status(Some(search.prefix()));
+        self.buf.redraw(writer)
+    }
+
+    /// Leave the search keeping the current buffer.
+    #[cfg(feature = "history")]
+    fn search_exit(&mut self) {
+        self.search = None;
+        self.buf.set_status(None);
+        self.buf.set_highlight(None);
+    }
+
+    /// Handle a key event while the history picker is open.
+    ///
+    /// All key events are consumed until an item is picked
+    /// or the picker is cancelled.
+    #[cfg(feature = "history")]
+    fn picker_key<W>(&mut self, writer: &mut W, event: &KeyEvent) -> Result<()>
+    where
+        W: Write,
+    {
+        let picker = self.picker.as_mut().unwrap();
+        let ctrl = event.modifiers == KeyModifiers::CONTROL;
+        match event.code {
+            KeyCode::Up => picker.select_previous(),
+            KeyCode::Char('p') if ctrl => picker.select_previous(),
+            KeyCode::Down => picker.select_next(),
+            KeyCode::Char('n') if ctrl => picker.select_next(),
+            KeyCode::Char('g') if ctrl => {
+                let saved = std::mem::take(&mut picker.saved);
+                return self.picker_exit(writer, saved);
+            }
+            KeyCode::Esc => {
+                let saved = std::mem::take(&mut picker.saved);
+                return self.picker_exit(writer, saved);
+            }
+            KeyCode::Enter => {
+                let history = self.options.history.as_ref().unwrap();
+                let history = history.lock().unwrap();
+                let value = match picker.selected() {
+                    Some(index) => history.items()[index].clone(),
+                    None => std::mem::take(&mut picker.saved),
+                };
+                drop(history);
+                return self.picker_exit(writer, value);
+            }
+            KeyCode::Char(c)
+                if !event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                picker.query.push(c);
+                return self.picker_filter(writer);
+            }
+            KeyCode::Backspace => {
+                picker.query.pop();
+                return self.picker_filter(writer);
+            }
+            _ => return Ok(()),
+        }
+        self.picker_render(writer)
+    }
+
+    /// Filter the history items using the picker query.
+    #[cfg(feature = "history")]
+    fn picker_filter<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let history = self.options.history.as_ref().unwrap();
+        let history = history.lock().unwrap();
+        self.picker.as_mut().unwrap().filter(history.items());
+        drop(history);
+        self.picker_render(writer)
+    }
+
+    /// Draw the picker query and the list of matches.
+    #[cfg(feature = "history")]
+    fn picker_render<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let history = self.options.history.as_ref().unwrap();
+        let history = history.lock().unwrap();
+        let picker = self.picker.as_ref().unwrap();
+        self.buf.set_status(Some(picker.prefix()));
+        self.buf.set_value(&picker.query, picker.query.len());
+        self.buf.set_info(picker.lines(history.items()));
+        drop(history);
+        self.buf.redraw(writer)
+    }
+
+    /// Close the picker and set the buffer.
+    #[cfg(feature = "history")]
+    fn picker_exit<W>(&mut self, writer: &mut W, value: String) -> Result<()>
+    where
+        W: Write,
+    {
+        self.picker = None;
+        self.buf.set_status(None);
+        self.buf.set_info(Vec::new());
+        self.buf.refresh(writer, value)
+    }
+
+    /// Clear the value and leave any sub-mode.
+    fn reset<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.argument = None;
+        self.delete_to_char = false;
+        self.meta = false;
+        #[cfg(feature = "history")]
+        {
+            self.search_exit();
+            self.picker = None;
+        }
+        #[cfg(feature = "completion")]
+        {
+            self.listing = None;
+        }
+        #[cfg(feature = "async")]
+        {
+            self.pending = None;
+        }
+        #[cfg(any(feature = "history", feature = "completion"))]
+        self.buf.set_info(Vec::new());
+        self.buf.refresh(writer, "")
+    }
+
+    /// Expand the abbreviation before the cursor.
+    ///
+    /// Returns the expansion when an abbreviation was expanded.
+    fn expand_abbreviation<W>(
+        &mut self,
+        writer: &mut W,
+    ) -> Result<Option<&'a str>>
+    where
+        W: Write,
+    {
+        if self.options.abbreviations.is_empty() {
+            return Ok(None);
+        }
+        let start = self.buf.word_start();
+        let word = &self.buf.buffer()[start..self.buf.cursor()];
+        if let Some(expansion) = self.options.abbreviations.get(word) {
+            let start_col = self.buf.column_of(start);
+            let end_col = self.buf.cursor_column();
+            self.buf
+                .replace_range(writer, start_col, end_col, expansion)?;
+            return Ok(Some(expansion));
+        }
+        Ok(None)
+    }
+
+    /// Ring the terminal bell unless the bell is disabled.
+    fn ring_bell<W>(&self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        if self.options.quiet {
+            return Ok(());
+        }
+        self.buf.ring_bell(writer)
+    }
+
+    /// Submit the value.
+    fn submit<W>(
+        &mut self,
+        writer: &mut W,
+        name: Option<&'static str>,
+    ) -> Result<Flow>
+    where
+        W: Write,
+    {
+        // A read only value is always submitted as it is
+        if self.buf.buffer().is_empty() && self.options.read_only.is_none() {
+            match &self.options.empty_accept {
+                EmptyAccept::Accept => {}
+                EmptyAccept::Reject => {
+                    self.ring_bell(writer)?;
+                    return Ok(Flow::Continue);
+                }
+                EmptyAccept::Default(value) => {
+                    self.buf.refresh(writer, value)?;
+                }
+            }
+        }
+
+        #[cfg(feature = "history")]
+        if let (Some(history), None) =
+            (&self.options.history, &self.options.read_only)
+        {
+            let mut history = history.lock().unwrap();
+            history.push(self.buf.buffer().to_string());
+        }
+
+        let mut redraw = false;
+        if let Some(colors) = &self.options.accept_colors {
+            let color = match self.validity() {
+                Validity::Valid => colors.valid,
+                _ => colors.invalid,
+            };
+            self.buf.set_color(Some(color));
+            redraw = true;
+        }
+        // A value shown while editing is masked again
+        if let Some(password) = &self.options.password {
+            let masked = self.buf.echo().is_some() || self.buf.is_hidden();
+            if !masked && !password.keep_shown {
+                self.buf.set_hidden(password.hidden);
+                self.buf.set_echo(password.echo);
+                redraw = true;
+            }
+        }
+        // The counter is only displayed while editing
+        if self.options.counter.is_some() {
+            self.buf.set_counter(None);
+            redraw = true;
+        }
+        if redraw {
+            self.buf.redraw(writer)?;
+        }
+
+        self.buf.finish(writer)?;
+        Ok(Flow::Submit(name))
+    }
+
+    /// Complete the word before the cursor.
+    ///
+    /// Returns whether the buffer was changed.
+    #[cfg(feature = "completion")]
+    fn complete<W>(&mut self, writer: &mut W) -> Result<bool>
+    where
+        W: Write,
+    {
+        let cursor = self.buf.cursor();
+        if let Some(completer) = &self.options.completer {
+            let (start, candidates) =
+                completer.complete(self.buf.buffer(), cursor);
+            return self.apply_completion(writer, start, candidates);
+        }
+
+        #[cfg(feature = "async")]
+        if let Some(completer) = &self.options.async_completer {
+            let receiver = completer.complete(self.buf.buffer(), cursor);
+            self.pending = Some(Pending {
+                receiver,
+                line: self.buf.buffer().to_string(),
+                cursor,
+                frame: 0,
+            });
+            self.buf.set_info(vec![spinner(0)]);
+            self.buf.redraw(writer)?;
+            return Ok(true);
+        }
+
+        if let Some(allowed) = &self.options.allowed {
+            let completer = WordCompleter::new(allowed.values.clone())
+                .ignore_case(!allowed.case_sensitive);
+            let (start, candidates) =
+                completer.complete(self.buf.buffer(), cursor);
+            return self.apply_completion(writer, start, candidates);
+        }
+        Ok(false)
+    }
+
+    /// Determine if the editor is waiting for completion candidates.
+    #[cfg(feature = "async")]
+    pub fn is_waiting(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Not waiting as asynchronous completion is not available.
+    #[cfg(not(feature = "async"))]
+    pub fn is_waiting(&self) -> bool {
+        false
+    }
+
+    /// Check for completion candidates advancing the spinner
+    /// when they are not ready.
+    #[cfg(feature = "async")]
+    pub fn tick<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let pending = match self.pending.as_mut() {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+        match pending.receiver.try_recv() {
+            Err(TryRecvError::Empty) => {
+                pending.frame += 1;
+                self.buf.set_info(vec![spinner(pending.frame)]);
+                self.buf.redraw(writer)
+            }
+            result => {
+                let pending = self.pending.take().unwrap();
+                self.buf.set_info(Vec::new());
+                let unchanged = pending.line == self.buf.buffer()
+                    && pending.cursor == self.buf.cursor();
+                match result {
+                    Ok((start, candidates)) if unchanged => {
+                        if !self.apply_completion(writer, start, candidates)? {
+                            self.buf.redraw(writer)?;
+                        }
+                        Ok(())
+                    }
+                    _ => self.buf.redraw(writer),
+                }
+            }
+        }
+    }
+
+    /// Asynchronous completion is not available.
+    #[cfg(not(feature = "async"))]
+    pub fn tick<W>(&mut self, _writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        Ok(())
+    }
+
+    /// Replace the word before the cursor with the candidates
+    /// or list the candidates when the word cannot be extended.
+    ///
+    /// Returns whether the buffer was changed.
+    #[cfg(feature = "completion")]
+    fn apply_completion<W>(
+        &mut self,
+        writer: &mut W,
+        start: usize,
+        candidates: Vec<Completion>,
+    ) -> Result<bool>
+    where
+        W: Write,
+    {
+        let cursor = self.buf.cursor();
+        let value = match candidates.len() {
+            1 => &candidates[0].value[..],
+            _ => {
+                // Only extend the word with the shared prefix
+                let prefix = common_prefix(&candidates);
+                if prefix.len() > cursor - start {
+                    prefix
+                } else {
+                    ""
+                }
+            }
+        };
+        if !value.is_empty() && self.buf.buffer()[start..cursor] != *value {
+            let value = value.to_string();
+            self.buf.replace(writer, start..cursor, &value)?;
+            self.completion_accepted(&value);
+            return Ok(true);
+        }
+        if candidates.len() > 1 {
+            self.listing = Some(Listing::new(start, candidates));
+            self.listing_render(writer)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Call the hook for an accepted completion.
+    #[cfg(feature = "completion")]
+    fn completion_accepted(&self, value: &str) {
+        if let Some(hook) = &self.options.on_completion {
+            let mut hook = hook.lock().unwrap();
+            (hook)(value);
+        }
+    }
+
+    /// Insert the next candidate from the completion listing.
+    #[cfg(feature = "completion")]
+    fn listing_next<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let listing = self.listing.as_mut().unwrap();
+        let start = listing.start;
+        let value = listing.select_next().to_string();
+        let cursor = self.buf.cursor();
+        self.buf.replace(writer, start..cursor, &value)?;
+        self.listing_render(writer)
+    }
+
+    /// Draw the completion listing below the prompt.
+    #[cfg(feature = "completion")]
+    fn listing_render<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let listing = self.listing.as_ref().unwrap();
+        let (width, _) = self.buf.size();
+        let columns = Columns::new(
+            &listing.candidates,
+            width as usize,
+            self.options.completion_columns,
+        );
+        self.buf
+            .set_info(columns.lines(&listing.candidates, listing.selected));
+        self.buf.redraw(writer)
+    }
+
+    /// Completion is not available.
+    #[cfg(not(feature = "completion"))]
+    fn complete<W>(&mut self, _writer: &mut W) -> Result<bool>
+    where
+        W: Write,
+    {
+        Ok(false)
+    }
+}
+
+/// Determine if text contains unclosed brackets or quotes.
+///
+/// Brackets inside quotes are ignored and a backslash
+/// escapes the next character.
+fn is_unbalanced(text: &str) -> bool {
+    let mut open = Vec::new();
+    let mut quote = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (_, '\\') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => open.push(')'),
+            (None, '[') => open.push(']'),
+            (None, '{') => open.push('}'),
+            (None, ')' | ']' | '}') if open.last() == Some(&c) => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+    quote.is_some() || !open.is_empty()
+}
+
+impl From<Editor<'_>> for String {
+    fn from(editor: Editor<'_>) -> Self {
+        editor.buf.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Indent, KeyBindings, MultiLine, PassWord, PasteWhitespace, Tab,
+    };
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent {
+            code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::CONTROL,
+        }
+    }
+
+    fn alt(c: char) -> KeyEvent {
+        KeyEvent {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::ALT,
+        }
+    }
+
+    fn type_str<W: Write>(
+        editor: &mut Editor<'_>,
+        writer: &mut W,
+        s: &str,
+    ) -> Result<()> {
+        for c in s.chars() {
+            editor.handle_key(writer, &key(KeyCode::Char(c)))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn editor_submit() -> Result<()> {
+        let options = PromptOptions::new();
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.set_size((80, 24));
+        editor.start(&mut writer, (0, 0))?;
+        type_str(&mut editor, &mut writer, "hello")?;
+        editor.handle_key(&mut writer, &key(KeyCode::Left))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Backspace))?;
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!(Flow::Submit(None), flow);
+        assert_eq!("helo", String::from(editor));
+        Ok(())
+    }
+
+    #[test]
+    fn tab_inserts_whitespace() -> Result<()> {
+        let options = PromptOptions::new().tab(Tab {
+            indent: Indent::Spaces(4),
+            complete: false,
+        });
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "a")?;
+        editor.handle_key(&mut writer, &key(KeyCode::Tab))?;
+        type_str(&mut editor, &mut writer, "b")?;
+        assert_eq!("a    b", editor.buf.buffer());
+
+        let options = PromptOptions::new().tab(Default::default());
+        let mut editor = Editor::new("> ", &options);
+        editor.handle_key(&mut writer, &key(KeyCode::Tab))?;
+        assert_eq!("\t", editor.buf.buffer());
+
+        let options = PromptOptions::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.handle_key(&mut writer, &key(KeyCode::Tab))?;
+        assert_eq!("", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[cfg(feature = "completion")]
+    #[test]
+    fn tab_completion_precedence() -> Result<()> {
+        use crate::completion::WordCompleter;
+        let completer =
+            || Box::new(WordCompleter::new(vec!["status".to_string()]));
+
+        let options = PromptOptions::new()
+            .completer(completer())
+            .tab(Default::default());
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "st")?;
+        editor.handle_key(&mut writer, &key(KeyCode::Tab))?;
+        assert_eq!("status", editor.buf.buffer());
+        // Nothing left to complete so whitespace is inserted
+        editor.handle_key(&mut writer, &key(KeyCode::Tab))?;
+        assert_eq!("status\t", editor.buf.buffer());
+
+        let options = PromptOptions::new().completer(completer()).tab(Tab {
+            indent: Indent::Spaces(2),
+            complete: false,
+        });
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "st")?;
+        editor.handle_key(&mut writer, &key(KeyCode::Tab))?;
+        assert_eq!("st  ", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[cfg(feature = "completion")]
+    #[test]
+    fn completion_listing() -> Result<()> {
+        use crate::completion::WordCompleter;
+        let words = (0..20).map(|i| format!("c{:02}", i)).collect();
+        let options =
+            PromptOptions::new().completer(Box::new(WordCompleter::new(words)));
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.set_size((40, 24));
+        editor.start(&mut writer, (0, 0))?;
+        type_str(&mut editor, &mut writer, "ls c")?;
+
+        let mut writer = Vec::new();
+        editor.handle_key(&mut writer, &key(KeyCode::Tab))?;
+        assert_eq!("ls c", editor.buf.buffer());
+        let out = String::from_utf8_lossy(&writer).into_owned();
+        assert!(out.contains("\r\n  c00    c04    c08    c12    c16"));
+        assert!(out.contains("\r\n  c03    c07    c11    c15    c19"));
+
+        // Tab inserts each candidate in turn
+        editor.handle_key(&mut writer, &key(KeyCode::Tab))?;
+        assert_eq!("ls c00", editor.buf.buffer());
+        editor.handle_key(&mut writer, &key(KeyCode::Tab))?;
+        assert_eq!("ls c01", editor.buf.buffer());
+        assert_eq!(Some(1), editor.listing.as_ref().unwrap().selected);
+
+        // Any other key closes the listing
+        let mut writer = Vec::new();
+        type_str(&mut editor, &mut writer, " ")?;
+        assert!(editor.listing.is_none());
+        assert_eq!("ls c01 ", editor.buf.buffer());
+        assert!(String::from_utf8_lossy(&writer).contains("\x1b[2;1H\x1b[J"));
+        Ok(())
+    }
+
+    #[cfg(feature = "completion")]
+    #[test]
+    fn on_completion() -> Result<()> {
+        use crate::completion::WordCompleter;
+        use std::{cell::RefCell, rc::Rc};
+        let words = vec!["cargo", "cat", "dog"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let accepted = Rc::new(RefCell::new(Vec::new()));
+        let hook = Rc::clone(&accepted);
+        let options = PromptOptions::new()
+            .completer(Box::new(WordCompleter::new(words)))
+            .on_completion(move |s| hook.borrow_mut().push(s.to_string()));
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.start(&mut writer, (0, 0))?;
+
+        // A single candidate and the shared prefix are accepted
+        type_str(&mut editor, &mut writer, "d")?;
+        editor.handle_key(&mut writer, &key(KeyCode::Tab))?;
+        type_str(&mut editor, &mut writer, " c")?;
+        editor.handle_key(&mut writer, &key(KeyCode::Tab))?;
+        assert_eq!("dog ca", editor.buf.buffer());
+        assert_eq!(vec!["dog", "ca"], *accepted.borrow());
+
+        // Ignoring the listing does not accept a candidate
+        editor.handle_key(&mut writer, &key(KeyCode::Tab))?;
+        assert!(editor.listing.is_some());
+        type_str(&mut editor, &mut writer, "t")?;
+        assert_eq!(2, accepted.borrow().len());
+
+        // Closing the listing accepts the selected candidate
+        editor.handle_key(&mut writer, &ctrl('w'))?;
+        type_str(&mut editor, &mut writer, "ca")?;
+        editor.handle_key(&mut writer, &key(KeyCode::Tab))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Tab))?;
+        assert_eq!(2, accepted.borrow().len());
+        type_str(&mut editor, &mut writer, " ")?;
+        assert_eq!("dog cargo ", editor.buf.buffer());
+        assert_eq!(vec!["dog", "ca", "cargo"], *accepted.borrow());
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_completion_spinner() -> Result<()> {
+        use crate::completion::{AsyncCompleter, PendingCompletion};
+        use std::sync::mpsc::{channel, Sender};
+        use std::sync::{Arc, Mutex};
+
+        type Senders = Arc<Mutex<Vec<Sender<(usize, Vec<Completion>)>>>>;
+        struct MockCompleter(Senders);
+        impl AsyncCompleter for MockCompleter {
+            fn complete(
+                &self,
+                _line: &str,
+                _position: usize,
+            ) -> PendingCompletion {
+                let (sender, receiver) = channel();
+                self.0.lock().unwrap().push(sender);
+                receiver
+            }
+        }
+
+        let senders: Senders = Default::default();
+        let options = PromptOptions::new()
+            .async_completer(Box::new(MockCompleter(Arc::clone(&senders))));
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.set_size((80, 24));
+        editor.start(&mut writer, (0, 0))?;
+        type_str(&mut editor, &mut writer, "git st")?;
+
+        let mut writer = Vec::new();
+        editor.handle_key(&mut writer, &key(KeyCode::Tab))?;
+        assert!(editor.is_waiting());
+        assert!(String::from_utf8_lossy(&writer).contains("\r\n| completing"));
+        let mut writer = Vec::new();
+        editor.tick(&mut writer)?;
+        assert!(String::from_utf8_lossy(&writer).contains("\x1b[2;1H/"));
+
+        let sender = senders.lock().unwrap().pop().unwrap();
+        sender.send((4, vec!["status".into()]))?;
+        let mut writer = Vec::new();
+        editor.tick(&mut writer)?;
+        assert!(!editor.is_waiting());
+        assert_eq!("git status", editor.buf.buffer());
+        let out = String::from_utf8_lossy(&writer).into_owned();
+        assert!(out.contains("\x1b[1;9Hatus\x1b[K\x1b[2;1H\x1b[J"));
+
+        // Candidates are discarded when the buffer changed
+        editor.handle_key(&mut writer, &key(KeyCode::Tab))?;
+        type_str(&mut editor, &mut writer, "x")?;
+        let sender = senders.lock().unwrap().pop().unwrap();
+        sender.send((4, vec!["stash".into()]))?;
+        editor.tick(&mut writer)?;
+        assert!(!editor.is_waiting());
+        assert_eq!("git statusx", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[test]
+    fn copy_word_and_yank() -> Result<()> {
+        let options = PromptOptions::new();
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "let value = ")?;
+        for _ in 0..7 {
+            editor.handle_key(&mut writer, &key(KeyCode::Left))?;
+        }
+        editor.action(&mut writer, KeyAction::CopyWord)?;
+        assert_eq!("let value = ", editor.buf.buffer());
+
+        editor.handle_key(&mut writer, &ctrl('e'))?;
+        editor.handle_key(&mut writer, &ctrl('y'))?;
+        assert_eq!("let value = value", editor.buf.buffer());
+
+        // Directly after a word
+        editor.action(&mut writer, KeyAction::CopyWord)?;
+        type_str(&mut editor, &mut writer, " ")?;
+        editor.handle_key(&mut writer, &ctrl('y'))?;
+        assert_eq!("let value = value value", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[test]
+    fn go_to_column() -> Result<()> {
+        let options = PromptOptions::new();
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "first\nhello world")?;
+        editor.handle_key(&mut writer, &alt('3'))?;
+        editor.action(&mut writer, KeyAction::GoToColumn)?;
+        assert_eq!("first\nhel|lo world", editor.buf.with_cursor_marker("|"));
+
+        // Arguments beyond the end of the line
+        editor.handle_key(&mut writer, &alt('1'))?;
+        editor.handle_key(&mut writer, &alt('2'))?;
+        editor.action(&mut writer, KeyAction::GoToColumn)?;
+        assert_eq!("first\nhello world|", editor.buf.with_cursor_marker("|"));
+
+        // The argument is cleared by other actions
+        editor.handle_key(&mut writer, &alt('5'))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Left))?;
+        editor.action(&mut writer, KeyAction::GoToColumn)?;
+        assert_eq!("first\n|hello world", editor.buf.with_cursor_marker("|"));
+
+        // Wide characters occupy two columns
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "日本語")?;
+        editor.handle_key(&mut writer, &alt('3'))?;
+        editor.action(&mut writer, KeyAction::GoToColumn)?;
+        assert_eq!("日|本語", editor.buf.with_cursor_marker("|"));
+        editor.handle_key(&mut writer, &alt('4'))?;
+        editor.action(&mut writer, KeyAction::GoToColumn)?;
+        assert_eq!("日本|語", editor.buf.with_cursor_marker("|"));
+
+        // Tabs expand to the tab stop after the prefix
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "a\tb")?;
+        editor.handle_key(&mut writer, &alt('6'))?;
+        editor.action(&mut writer, KeyAction::GoToColumn)?;
+        assert_eq!("a\t|b", editor.buf.with_cursor_marker("|"));
+        editor.handle_key(&mut writer, &alt('5'))?;
+        editor.action(&mut writer, KeyAction::GoToColumn)?;
+        assert_eq!("a|\tb", editor.buf.with_cursor_marker("|"));
+        Ok(())
+    }
+
+    #[test]
+    fn rotate_kill_ring() -> Result<()> {
+        let f2 = KeyEvent::new(KeyCode::F(2), KeyModifiers::NONE);
+        let options = PromptOptions::new().bindings(
+            KeyBindings::default().bind(f2, vec![KeyAction::RotateKillRing]),
+        );
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "one two three")?;
+        for _ in 0..3 {
+            editor.handle_key(&mut writer, &ctrl('w'))?;
+        }
+        assert_eq!("", editor.buf.buffer());
+
+        editor.handle_key(&mut writer, &f2)?;
+        assert_eq!("", editor.buf.buffer());
+        editor.handle_key(&mut writer, &ctrl('y'))?;
+        assert_eq!("two ", editor.buf.buffer());
+        editor.handle_key(&mut writer, &f2)?;
+        editor.handle_key(&mut writer, &ctrl('y'))?;
+        assert_eq!("two three", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[test]
+    fn key_hook_consume() -> Result<()> {
+        let options = PromptOptions::new().on_key(|event| match event.code {
+            KeyCode::F(1) => Intercept::Consume,
+            KeyCode::Char('x') => Intercept::Consume,
+            _ => Intercept::Continue,
+        });
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "axb")?;
+        editor.handle_key(&mut writer, &key(KeyCode::F(1)))?;
+        assert_eq!("ab", editor.buf.buffer());
+
+        // The pending delete to char and reset line keys reach the hook
+        let bindings =
+            KeyBindings::default().bind(ctrl('g'), vec![KeyAction::ResetLine]);
+        let options = PromptOptions::new().bindings(bindings).on_key(|event| {
+            match (event.code, event.modifiers) {
+                (KeyCode::Char('x'), _) => Intercept::Consume,
+                (KeyCode::Char('g'), KeyModifiers::CONTROL) => {
+                    Intercept::Consume
+                }
+                _ => Intercept::Continue,
+            }
+        });
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "abc")?;
+        editor.action(&mut writer, KeyAction::DeleteToChar)?;
+        editor.handle_key(&mut writer, &key(KeyCode::Char('x')))?;
+        editor.handle_key(&mut writer, &ctrl('g'))?;
+        assert_eq!("abc", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[test]
+    fn key_hook_override() -> Result<()> {
+        let mut count = 0;
+        let options = PromptOptions::new().on_key(move |event| {
+            count += 1;
+            match event.code {
+                KeyCode::Char('!') if count > 2 => {
+                    Intercept::Override(KeyAction::SubmitLine)
+                }
+                _ => Intercept::Continue,
+            }
+        });
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "!a")?;
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Char('!')))?;
+        assert_eq!(Flow::Submit(None), flow);
+        assert_eq!("!a", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[test]
+    fn submit_named() -> Result<()> {
+        let alt_enter = KeyEvent {
+            code: KeyCode::Enter,
+            modifiers: KeyModifiers::ALT,
+        };
+        let ctrl_s = ctrl('s');
+        let options =
+            PromptOptions::new().multiline(Default::default()).bindings(
+                KeyBindings::default()
+                    .bind(alt_enter, vec![KeyAction::SubmitAs("save")])
+                    .bind(ctrl_s, vec![KeyAction::SubmitAs("save-close")]),
+            );
+
+        for (event, name) in [(alt_enter, "save"), (ctrl_s, "save-close")] {
+            let mut writer = Vec::new();
+            let mut editor = Editor::new("> ", &options);
+            type_str(&mut editor, &mut writer, "a")?;
+            // Enter in multiline mode does not submit
+            let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+            assert_eq!(Flow::Continue, flow);
+            let flow = editor.handle_key(&mut writer, &event)?;
+            assert_eq!(Flow::Submit(Some(name)), flow);
+            assert_eq!("a\n", editor.buf.buffer());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn kill_word_to_kill_ring() -> Result<()> {
+        let options = PromptOptions::new();
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "foo bar")?;
+        editor.handle_key(&mut writer, &ctrl('w'))?;
+        assert_eq!(Some("bar"), editor.kill_ring.yank());
+        assert_eq!("foo ", editor.buf.buffer());
+
+        editor.handle_key(&mut writer, &ctrl('a'))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Right))?;
+        editor.handle_key(&mut writer, &ctrl('w'))?;
+        assert_eq!(Some("f"), editor.kill_ring.yank());
+        assert_eq!("oo ", editor.buf.buffer());
+
+        // Nothing erased at the start of the buffer
+        editor.handle_key(&mut writer, &ctrl('w'))?;
+        assert_eq!(Some("f"), editor.kill_ring.yank());
+
+        editor.handle_key(&mut writer, &ctrl('e'))?;
+        editor.handle_key(&mut writer, &ctrl('y'))?;
+        assert_eq!("oo f", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[test]
+    fn delete_to_char() -> Result<()> {
+        let options = PromptOptions::new().bindings(
+            KeyBindings::default()
+                .bind(alt('t'), vec![KeyAction::DeleteToChar]),
+        );
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "foo(bar, baz)")?;
+        editor.handle_key(&mut writer, &ctrl('a'))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Right))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Right))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Right))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Right))?;
+        editor.handle_key(&mut writer, &alt('t'))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Char(',')))?;
+        assert_eq!("foo(|, baz)", editor.buf.with_cursor_marker("|"));
+        assert_eq!(Some("bar"), editor.kill_ring.yank());
+
+        // The target is not in the buffer
+        let mut writer = Vec::new();
+        editor.handle_key(&mut writer, &alt('t'))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Char('x')))?;
+        assert_eq!(b"\x07", &writer[..]);
+        assert_eq!("foo(|, baz)", editor.buf.with_cursor_marker("|"));
+
+        // Keys are handled normally after the target
+        editor.handle_key(&mut writer, &key(KeyCode::Char('z')))?;
+        assert_eq!("foo(z|, baz)", editor.buf.with_cursor_marker("|"));
+        Ok(())
+    }
+
+    #[test]
+    fn is_action_enabled() -> Result<()> {
+        let options =
+            PromptOptions::new().is_action_enabled(|action, value| {
+                *action != KeyAction::SubmitLine || value.len() >= 3
+            });
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.start(&mut writer, (0, 0))?;
+        type_str(&mut editor, &mut writer, "ab")?;
+
+        let mut writer = Vec::new();
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!(Flow::Continue, flow);
+        assert_eq!("\x07", String::from_utf8_lossy(&writer));
+
+        type_str(&mut editor, &mut writer, "c")?;
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!(Flow::Submit(None), flow);
+        assert_eq!("abc", editor.buf.buffer());
+
+        // Disabled actions discard the argument and may be silent
+        let options = PromptOptions::new()
+            .is_action_enabled(|action, _| *action != KeyAction::MoveToLineEnd)
+            .silent_disabled(true);
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "abcd")?;
+        let mut writer = Vec::new();
+        editor.handle_key(&mut writer, &alt('2'))?;
+        editor.action(&mut writer, KeyAction::MoveToLineEnd)?;
+        assert!(!String::from_utf8_lossy(&writer).contains('\x07'));
+        editor.action(&mut writer, KeyAction::GoToColumn)?;
+        assert_eq!("|abcd", editor.buf.with_cursor_marker("|"));
+        Ok(())
+    }
+
+    #[test]
+    fn counter() -> Result<()> {
+        let options = PromptOptions::new().counter(Counter {
+            maximum: Some(4),
+            ..Default::default()
+        });
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.start(&mut writer, (0, 0))?;
+        let mut writer = Vec::new();
+        type_str(&mut editor, &mut writer, "ab")?;
+        assert!(String::from_utf8_lossy(&writer).contains("\x1b[2;1H2/4"));
+
+        // Removed when the line is submitted
+        let mut writer = Vec::new();
+        editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        let out = String::from_utf8_lossy(&writer).into_owned();
+        assert!(out.contains("\x1b[2;1H\x1b[J"));
+        assert!(!out.contains("2/4"));
+
+        // The maximum defaults to the auto submit length
+        let options = PromptOptions::new()
+            .auto_submit(3)
+            .counter(Default::default());
+        let mut editor = Editor::new("> ", &options);
+        editor.start(&mut writer, (0, 0))?;
+        let mut writer = Vec::new();
+        type_str(&mut editor, &mut writer, "ab")?;
+        assert!(String::from_utf8_lossy(&writer).contains("\x1b[2;1H2/3"));
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Char('c')))?;
+        assert_eq!(Flow::Submit(None), flow);
+        Ok(())
+    }
+
+    #[test]
+    fn auto_submit() -> Result<()> {
+        let options =
+            PromptOptions::new()
+                .auto_submit(6)
+                .on_key(|event: &KeyEvent| match event.code {
+                    KeyCode::Char(c) if !c.is_ascii_digit() => {
+                        Intercept::Consume
+                    }
+                    _ => Intercept::Continue,
+                });
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        for c in "12a345".chars() {
+            let flow =
+                editor.handle_key(&mut writer, &key(KeyCode::Char(c)))?;
+            assert_eq!(Flow::Continue, flow);
+        }
+        assert_eq!("12345", editor.buf.buffer());
+
+        // Removing a digit keeps editing until the sixth digit
+        editor.handle_key(&mut writer, &key(KeyCode::Backspace))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Char('5')))?;
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Char('6')))?;
+        assert_eq!(Flow::Submit(None), flow);
+        assert_eq!("123456", editor.buf.buffer());
+
+        let mut editor = Editor::new("> ", &options);
+        assert_eq!(Flow::Continue, editor.paste(&mut writer, "12")?);
+        assert_eq!(Flow::Submit(None), editor.paste(&mut writer, "3456")?);
+        assert_eq!("123456", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[cfg(feature = "timestamp")]
+    #[test]
+    fn insert_timestamp() -> Result<()> {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        // 2024-02-29 13:05:09 UTC
+        let clock = || UNIX_EPOCH + Duration::from_secs(1709211909);
+        let options =
+            PromptOptions::new().timestamp_format("at %s:").clock(clock);
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.action(&mut writer, KeyAction::InsertTimestamp)?;
+        assert_eq!("at 1709211909:", editor.buf.buffer());
+
+        // Invalid formats use the default format
+        let options = PromptOptions::new().timestamp_format("%Q").clock(clock);
+        let mut editor = Editor::new("> ", &options);
+        editor.action(&mut writer, KeyAction::InsertTimestamp)?;
+        assert_eq!("2024-02-29 13:05:09", editor.buf.buffer());
+
+        // The system clock is used by default
+        let options = PromptOptions::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.action(&mut writer, KeyAction::InsertTimestamp)?;
+        assert_eq!(19, editor.buf.buffer().len());
+        Ok(())
+    }
+
+    #[test]
+    fn escape_action() -> Result<()> {
+        let esc = key(KeyCode::Esc);
+        let options = PromptOptions::new().escape(EscapeAction::Meta);
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "foo/bar")?;
+        editor.handle_key(&mut writer, &esc)?;
+        assert_eq!("foo/bar", editor.buf.buffer());
+        editor.handle_key(&mut writer, &key(KeyCode::Backspace))?;
+        assert_eq!("foo/", editor.buf.buffer());
+        // Only the next key is composed
+        editor.handle_key(&mut writer, &key(KeyCode::Backspace))?;
+        assert_eq!("foo", editor.buf.buffer());
+
+        // Escape then f composes into Alt+f to move forward a word
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "one two")?;
+        editor.action(&mut writer, KeyAction::MoveToLineBegin)?;
+        editor.handle_key(&mut writer, &esc)?;
+        editor.handle_key(&mut writer, &key(KeyCode::Char('f')))?;
+        assert_eq!("one| two", editor.buf.with_cursor_marker("|"));
+        editor.handle_key(&mut writer, &alt('b'))?;
+        assert_eq!("|one two", editor.buf.with_cursor_marker("|"));
+
+        let options = PromptOptions::new().escape(EscapeAction::Clear);
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "foo")?;
+        assert_eq!(Flow::Continue, editor.handle_key(&mut writer, &esc)?);
+        assert_eq!("", editor.buf.buffer());
+
+        let options = PromptOptions::new().escape(EscapeAction::Abort);
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "foo")?;
+        assert_eq!(Flow::Abort, editor.handle_key(&mut writer, &esc)?);
+
+        // Ignored by default
+        let options = PromptOptions::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "foo")?;
+        assert_eq!(Flow::Continue, editor.handle_key(&mut writer, &esc)?);
+        assert_eq!("foo", editor.buf.buffer());
+
+        // The key hook and bindings see escape
+        let bindings =
+            KeyBindings::default().bind(esc, vec![KeyAction::SubmitLine]);
+        let options = PromptOptions::new()
+            .escape(EscapeAction::Abort)
+            .bindings(bindings);
+        let mut editor = Editor::new("> ", &options);
+        assert_eq!(Flow::Submit(None), editor.handle_key(&mut writer, &esc)?);
+
+        let options = PromptOptions::new().escape(EscapeAction::Abort).on_key(
+            |event: &KeyEvent| match event.code {
+                KeyCode::Esc => Intercept::Consume,
+                _ => Intercept::Continue,
+            },
+        );
+        let mut editor = Editor::new("> ", &options);
+        assert_eq!(Flow::Continue, editor.handle_key(&mut writer, &esc)?);
+        Ok(())
+    }
+
+    #[test]
+    fn quiet() -> Result<()> {
+        let bell = |options: &PromptOptions| -> Result<bool> {
+            let mut writer = Vec::new();
+            let mut editor = Editor::new("> ", options);
+            editor.action(&mut writer, KeyAction::EraseCharacter)?;
+            Ok(writer.contains(&0x07))
+        };
+        let options = PromptOptions::new()
+            .empty_accept(EmptyAccept::Reject)
+            .empty_backspace(EmptyBackspace::Bell);
+        assert!(bell(&options)?);
+        let options = options.quiet(true);
+        assert!(!bell(&options)?);
+
+        // Overrides the bell for other behaviors
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert!(!writer.contains(&0x07));
+        Ok(())
+    }
+
+    #[test]
+    fn transpose_forward() -> Result<()> {
+        let options = PromptOptions::new();
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "abc")?;
+        editor.handle_key(&mut writer, &ctrl('a'))?;
+        editor.action(&mut writer, KeyAction::TransposeForward)?;
+        editor.action(&mut writer, KeyAction::TransposeForward)?;
+        assert_eq!("bc|a", editor.buf.with_cursor_marker("|"));
+
+        // Does nothing at the last position
+        let mut writer = Vec::new();
+        editor.action(&mut writer, KeyAction::TransposeForward)?;
+        assert!(!writer.contains(&0x07));
+        assert_eq!("bc|a", editor.buf.with_cursor_marker("|"));
+        Ok(())
+    }
+
+    #[test]
+    fn horizontal_space() -> Result<()> {
+        let options = PromptOptions::new();
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "a   b  \t c")?;
+        editor.handle_key(&mut writer, &key(KeyCode::Left))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Left))?;
+        editor.handle_key(&mut writer, &alt(' '))?;
+        assert_eq!("a   b |c", editor.buf.with_cursor_marker("|"));
+
+        editor.handle_key(&mut writer, &ctrl('a'))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Right))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Right))?;
+        editor.handle_key(&mut writer, &alt('\\'))?;
+        assert_eq!("a|b c", editor.buf.with_cursor_marker("|"));
+        Ok(())
+    }
+
+    fn alt_backspace() -> KeyEvent {
+        KeyEvent::new(KeyCode::Backspace, KeyModifiers::ALT)
+    }
+
+    #[test]
+    fn backspace_variations() -> Result<()> {
+        let options = PromptOptions::new();
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.start(&mut writer, (0, 0))?;
+        type_str(&mut editor, &mut writer, "abcdef")?;
+        editor.handle_key(&mut writer, &key(KeyCode::Char('\x7f')))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Char('\x08')))?;
+        editor.handle_key(&mut writer, &ctrl('h'))?;
+        assert_eq!("abc", editor.buf.buffer());
+
+        // Delete erases the character under the cursor
+        editor.handle_key(&mut writer, &key(KeyCode::Left))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Left))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Delete))?;
+        assert_eq!("a|c", editor.buf.with_cursor_marker("|"));
+        Ok(())
+    }
+
+    #[test]
+    fn unix_word_rubout() -> Result<()> {
+        let options = PromptOptions::new();
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "foo/bar baz")?;
+        for _ in 0..4 {
+            editor.handle_key(&mut writer, &key(KeyCode::Left))?;
+        }
+        editor.handle_key(&mut writer, &ctrl('w'))?;
+        assert_eq!("| baz", editor.buf.with_cursor_marker("|"));
+        assert_eq!(Some("foo/bar"), editor.kill_ring.yank());
+
+        // Word boundaries stop at punctuation
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "foo/bar")?;
+        editor.handle_key(&mut writer, &alt_backspace())?;
+        assert_eq!("foo/", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[test]
+    fn kill_word_stop_at_whitespace() -> Result<()> {
+        let kill = |options: &PromptOptions| -> Result<String> {
+            let mut writer = Vec::new();
+            let mut editor = Editor::new("> ", options);
+            type_str(&mut editor, &mut writer, "git   commit  ")?;
+            editor.handle_key(&mut writer, &alt_backspace())?;
+            Ok(editor.buf.buffer().to_string())
+        };
+        assert_eq!("git   ", kill(&PromptOptions::new())?);
+        assert_eq!(
+            "git   commit",
+            kill(&PromptOptions::new().stop_at_whitespace(true))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn select_all_region() -> Result<()> {
+        let options = PromptOptions::new();
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "foo bar")?;
+        editor.handle_key(&mut writer, &ctrl('a'))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Right))?;
+        editor.action(&mut writer, KeyAction::SelectAll)?;
+        assert_eq!(Some(0..7), editor.buf.region());
+        assert_eq!(7, editor.buf.cursor());
+
+        editor.action(&mut writer, KeyAction::KillRegion)?;
+        assert_eq!("", editor.buf.buffer());
+        assert_eq!(Some("foo bar"), editor.kill_ring.yank());
+        Ok(())
+    }
+
+    #[test]
+    fn wrap_region() -> Result<()> {
+        let options = PromptOptions::new().bindings(
+            KeyBindings::default()
+                .bind(alt('q'), vec![KeyAction::WrapRegion('\'', '\'')]),
+        );
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "ls ")?;
+        editor.handle_key(&mut writer, &alt('q'))?;
+        type_str(&mut editor, &mut writer, "a b")?;
+        assert_eq!("ls 'a b|'", editor.buf.with_cursor_marker("|"));
+        Ok(())
+    }
+
+    #[test]
+    fn backspace_empty() -> Result<()> {
+        let options = PromptOptions::new();
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.start(&mut writer, (0, 0))?;
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Backspace))?;
+        assert_eq!(Flow::Continue, flow);
+        assert!(!writer.contains(&0x07));
+
+        let options =
+            PromptOptions::new().empty_backspace(EmptyBackspace::Bell);
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Backspace))?;
+        assert_eq!(Flow::Continue, flow);
+        assert_eq!(b"\x07", &writer[..]);
+
+        let options =
+            PromptOptions::new().empty_backspace(EmptyBackspace::Back);
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "a")?;
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Backspace))?;
+        assert_eq!(Flow::Continue, flow);
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Backspace))?;
+        assert_eq!(Flow::Back, flow);
+        Ok(())
+    }
+
+    #[test]
+    fn hidden_password() -> Result<()> {
+        let options = PromptOptions::new().password(PassWord::hidden());
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.set_size((80, 24));
+        editor.start(&mut writer, (0, 0))?;
+
+        let mut writer = Vec::new();
+        type_str(&mut editor, &mut writer, "xyz")?;
+        editor.handle_key(&mut writer, &key(KeyCode::Left))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Backspace))?;
+        let out = String::from_utf8_lossy(&writer).into_owned();
+        assert!(!out.contains(|c| "xyz*".contains(c)));
+        assert!(out.ends_with("\x1b[1;3H"));
+        assert_eq!("xz", editor.buf.buffer());
+        assert_eq!("> ", editor.visible_line());
+
+        // Showing the value and hiding it again
+        editor.action(&mut writer, KeyAction::ToggleMask)?;
+        assert_eq!("> xz", editor.visible_line());
+        editor.action(&mut writer, KeyAction::ToggleMask)?;
+        assert_eq!("> ", editor.visible_line());
+
+        // Submitting leaves the cursor on the row below the prefix
+        let mut writer = Vec::new();
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!(Flow::Submit(None), flow);
+        assert_eq!("\x1b[1;1H\r\n", String::from_utf8_lossy(&writer));
+        Ok(())
+    }
+
+    #[test]
+    fn password_masked_on_submit() -> Result<()> {
+        let submit = |password: PassWord| -> Result<String> {
+            let options = PromptOptions::new().password(password);
+            let mut writer = Vec::new();
+            let mut editor = Editor::new("> ", &options);
+            editor.start(&mut writer, (0, 0))?;
+            type_str(&mut editor, &mut writer, "xyz")?;
+            editor.action(&mut writer, KeyAction::ToggleMask)?;
+            assert_eq!("> xyz", editor.visible_line());
+            editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+            Ok(editor.visible_line())
+        };
+        assert_eq!("> ", submit(PassWord::hidden())?);
+        assert_eq!("> ***", submit(Default::default())?);
+        let shown = submit(PassWord {
+            keep_shown: true,
+            ..Default::default()
+        })?;
+        assert_eq!("> xyz", shown);
+        Ok(())
+    }
+
+    #[cfg(feature = "history")]
+    #[test]
+    fn reset_line() -> Result<()> {
+        use crate::history::{History, MemoryHistory};
+        use std::sync::Mutex;
+
+        let mut history = MemoryHistory::new(Default::default());
+        history.push("git status".to_string());
+        let bindings = KeyBindings::default()
+            .bind(ctrl('c'), vec![KeyAction::ResetLine])
+            .bind(alt('1'), vec![KeyAction::DigitArgument(1)]);
+        let options = PromptOptions::new()
+            .bindings(bindings)
+            .history(Box::new(Mutex::new(history)));
+
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "echo foo")?;
+        editor.handle_key(&mut writer, &ctrl('w'))?;
+        editor.handle_key(&mut writer, &alt('1'))?;
+        editor.handle_key(&mut writer, &ctrl('r'))?;
+        type_str(&mut editor, &mut writer, "sta")?;
+        assert_eq!("git status", editor.buf.buffer());
+
+        let flow = editor.handle_key(&mut writer, &ctrl('c'))?;
+        assert_eq!(Flow::Continue, flow);
+        assert!(editor.search.is_none());
+        assert!(editor.argument.is_none());
+        assert_eq!("> ", editor.visible_line());
+
+        // Editing continues normally keeping the kill ring
+        type_str(&mut editor, &mut writer, "s")?;
+        editor.handle_key(&mut writer, &ctrl('y'))?;
+        assert_eq!("sfoo", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[cfg(feature = "history")]
+    #[test]
+    fn reverse_search_highlight() -> Result<()> {
+        use crate::history::{History, MemoryHistory};
+        use std::sync::Mutex;
+
+        let mut history = MemoryHistory::new(Default::default());
+        history.push("git status".to_string());
+        history.push("ls -la".to_string());
+        history.push("git stash".to_string());
+        let options =
+            PromptOptions::new().history(Box::new(Mutex::new(history)));
+
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "echo")?;
+        editor.handle_key(&mut writer, &ctrl('r'))?;
+
+        let mut writer = Vec::new();
+        type_str(&mut editor, &mut writer, "sta")?;
+        assert_eq!("git |stash", editor.buf.with_cursor_marker("|"));
+        let out = String::from_utf8_lossy(&writer).into_owned();
+        // Only the changes to the search are written
+        assert!(out.contains("a': git \x1b[4msta\x1b[24msh"));
+
+        // Older match
+        let mut writer = Vec::new();
+        editor.handle_key(&mut writer, &ctrl('r'))?;
+        assert_eq!("git status", editor.buf.buffer());
+        let out = String::from_utf8_lossy(&writer).into_owned();
+        assert!(out.contains("\x1b[4msta\x1b[24mtus"));
+
+        // No more matches
+        let mut writer = Vec::new();
+        editor.handle_key(&mut writer, &ctrl('r'))?;
+        let out = String::from_utf8_lossy(&writer).into_owned();
+        assert!(out.contains("failed reverse-i-search)`sta'"));
+        assert_eq!("git status", editor.buf.buffer());
+
+        // Accept the match and move to the end of the line
+        let mut writer = Vec::new();
+        editor.handle_key(&mut writer, &ctrl('e'))?;
+        assert!(editor.search.is_none());
+        assert_eq!("git status|", editor.buf.with_cursor_marker("|"));
+        let out = String::from_utf8_lossy(&writer).into_owned();
+        assert!(!out.contains("\x1b[4m"));
+        assert!(out.contains("> git status"));
+
+        // Cancel restores the original buffer
+        editor.handle_key(&mut writer, &ctrl('r'))?;
+        type_str(&mut editor, &mut writer, "ls")?;
+        assert_eq!("ls -la", editor.buf.buffer());
+        editor.handle_key(&mut writer, &ctrl('g'))?;
+        assert_eq!("git status", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[test]
+    fn toggle_comment() -> Result<()> {
+        let options = PromptOptions::new().multiline(Default::default());
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "echo a")?;
+        editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        type_str(&mut editor, &mut writer, "echo b")?;
+        editor.handle_key(&mut writer, &key(KeyCode::Left))?;
+        editor.action(&mut writer, KeyAction::ToggleComment)?;
+        assert_eq!("echo a\n# echo |b", editor.buf.with_cursor_marker("|"));
+        editor.action(&mut writer, KeyAction::ToggleComment)?;
+        assert_eq!("echo a\necho |b", editor.buf.with_cursor_marker("|"));
+
+        let options = PromptOptions::new().comment("-- ");
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "select")?;
+        editor.action(&mut writer, KeyAction::ToggleComment)?;
+        assert_eq!("-- select|", editor.buf.with_cursor_marker("|"));
+        Ok(())
+    }
+
+    #[test]
+    fn read_only() -> Result<()> {
+        let options = PromptOptions::new()
+            .read_only("fixed")
+            .multiline(Default::default());
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.set_size((80, 24));
+        editor.start(&mut writer, (0, 0))?;
+        assert!(String::from_utf8_lossy(&writer).contains("> fixed"));
+
+        type_str(&mut editor, &mut writer, "abc")?;
+        editor.handle_key(&mut writer, &key(KeyCode::Left))?;
+        editor.handle_key(&mut writer, &key(KeyCode::Backspace))?;
+        editor.handle_key(&mut writer, &ctrl('w'))?;
+        editor.paste(&mut writer, "pasted")?;
+        assert_eq!("fixe|d", editor.buf.with_cursor_marker("|"));
+
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!(Flow::Submit(None), flow);
+        assert_eq!("fixed", String::from(editor));
+        Ok(())
+    }
+
+    #[test]
+    fn accept_colors() -> Result<()> {
+        use crate::{AcceptColors, Validation};
+        use crossterm::style::{Color, SetForegroundColor};
+
+        let green = SetForegroundColor(Color::Green).to_string();
+        let red = SetForegroundColor(Color::Red).to_string();
+        let submit = |options: &PromptOptions, value: &str| -> Result<String> {
+            let mut writer = Vec::new();
+            let mut editor = Editor::new("> ", options);
+            type_str(&mut editor, &mut writer, value)?;
+            let mut writer = Vec::new();
+            editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+            Ok(String::from_utf8_lossy(&writer).into_owned())
+        };
+
+        let options = PromptOptions::new();
+        let out = submit(&options, "ok")?;
+        assert!(!out.contains(&green) && !out.contains(&red));
+
+        let options = PromptOptions::new()
+            .validation(Validation {
+                validate: Box::new(|s| s == "ok"),
+            })
+            .accept_colors(Default::default());
+        let out = submit(&options, "ok")?;
+        assert!(out.contains(&format!("{}> ok\x1b[0m", green)));
+        let out = submit(&options, "no")?;
+        assert!(out.contains(&format!("{}> no\x1b[0m", red)));
+
+        // Without validation every value is valid
+        let options = PromptOptions::new().accept_colors(AcceptColors {
+            valid: Color::Blue,
+            ..Default::default()
+        });
+        let blue = SetForegroundColor(Color::Blue).to_string();
+        assert!(submit(&options, "any")?.contains(&blue));
+        Ok(())
+    }
+
+    #[test]
+    fn empty_accept() -> Result<()> {
+        let options = PromptOptions::new();
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!(Flow::Submit(None), flow);
+        assert_eq!("", editor.buf.buffer());
+
+        let options = PromptOptions::new().empty_accept(EmptyAccept::Reject);
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!(Flow::Continue, flow);
+        assert_eq!(b"\x07", &writer[..]);
+        type_str(&mut editor, &mut writer, "a")?;
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!(Flow::Submit(None), flow);
+        assert_eq!("a", editor.buf.buffer());
+
+        let options = PromptOptions::new()
+            .empty_accept(EmptyAccept::Default("yes".to_string()));
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!(Flow::Submit(None), flow);
+        assert_eq!("yes", editor.buf.buffer());
+
+        // An empty read only value is not replaced
+        let options = PromptOptions::new()
+            .read_only("")
+            .empty_accept(EmptyAccept::Default("yes".to_string()));
+        let mut editor = Editor::new("> ", &options);
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!(Flow::Submit(None), flow);
+        assert_eq!("", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[cfg(feature = "history")]
+    #[test]
+    fn history_toggle() -> Result<()> {
+        use crate::history::{History, MemoryHistory};
+        use std::sync::Mutex;
+
+        let mut history = MemoryHistory::new(Default::default());
+        history.push("cargo build".to_string());
+        history.push("cargo test".to_string());
+        let options =
+            PromptOptions::new().history(Box::new(Mutex::new(history)));
+
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "echo")?;
+        editor.action(&mut writer, KeyAction::HistoryToggle)?;
+        assert_eq!("cargo test", editor.buf.buffer());
+        editor.action(&mut writer, KeyAction::HistoryToggle)?;
+        assert_eq!("cargo build", editor.buf.buffer());
+        editor.action(&mut writer, KeyAction::HistoryToggle)?;
+        assert_eq!("cargo test", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[test]
+    fn toggle_mask() -> Result<()> {
+        let options = PromptOptions::new().password(Default::default());
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.set_size((80, 24));
+        editor.start(&mut writer, (0, 0))?;
+        type_str(&mut editor, &mut writer, "日本")?;
+
+        let mut writer = Vec::new();
+        editor.action(&mut writer, KeyAction::ToggleMask)?;
+        let out = String::from_utf8_lossy(&writer).into_owned();
+        assert!(out.contains("\x1b[1;3H日本\x1b[K\x1b[1;7H"));
+        assert_eq!("日本", editor.buf.buffer());
+
+        let mut writer = Vec::new();
+        editor.action(&mut writer, KeyAction::ToggleMask)?;
+        let out = String::from_utf8_lossy(&writer).into_owned();
+        assert!(out.contains("\x1b[1;3H**\x1b[K\x1b[1;5H"));
+        assert_eq!("日本", editor.buf.buffer());
+
+        // No effect without password options
+        let options = PromptOptions::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.action(&mut writer, KeyAction::ToggleMask)?;
+        assert_eq!(None, editor.buf.echo());
+        Ok(())
+    }
+
+    #[cfg(feature = "history")]
+    #[test]
+    fn history_picker() -> Result<()> {
+        use crate::history::{History, MemoryHistory};
+        use std::sync::Mutex;
+
+        let mut history = MemoryHistory::new(Default::default());
+        history.push("git status".to_string());
+        history.push("ls -la".to_string());
+        history.push("git stash".to_string());
+        history.push("cargo test".to_string());
+        let options =
+            PromptOptions::new().history(Box::new(Mutex::new(history)));
+
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.set_size((80, 24));
+        editor.start(&mut writer, (0, 0))?;
+        type_str(&mut editor, &mut writer, "echo")?;
+        editor.action(&mut writer, KeyAction::HistoryPicker)?;
+        assert_eq!("", editor.buf.buffer());
+        assert_eq!(1, editor.buf.row_count());
+
+        type_str(&mut editor, &mut writer, "gsta")?;
+        let mut writer = Vec::new();
+        editor.set_size((80, 24));
+        editor.redraw(&mut writer)?;
+        let out = String::from_utf8_lossy(&writer).into_owned();
+        assert!(out.contains("(2/2) gsta\r\n> git stash\r\n  git status"));
+        assert!(!out.contains("cargo test"));
+
+        editor.handle_key(&mut writer, &key(KeyCode::Down))?;
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!(Flow::Continue, flow);
+        assert_eq!("git status", editor.buf.buffer());
+        assert!(editor.picker.is_none());
+        assert!(String::from_utf8_lossy(&writer)
+            .ends_with("\x1b[2;1H\x1b[J\x1b[1;13H"));
+
+        // Cancel restores the buffer
+        editor.action(&mut writer, KeyAction::HistoryPicker)?;
+        type_str(&mut editor, &mut writer, "ls")?;
+        editor.handle_key(&mut writer, &key(KeyCode::Esc))?;
+        assert_eq!("git status", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[test]
+    fn expand_abbreviations() -> Result<()> {
+        let options = PromptOptions::new()
+            .abbreviation("gco", "git checkout")
+            .abbreviation("gst", "git status ");
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "gco ")?;
+        assert_eq!("git checkout ", editor.buf.buffer());
+        type_str(&mut editor, &mut writer, "xgco gst ")?;
+        assert_eq!("git checkout xgco git status ", editor.buf.buffer());
+
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "gst")?;
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!(Flow::Submit(None), flow);
+        assert_eq!("git status ", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn edit_in_editor() -> Result<()> {
+        let options = PromptOptions::new().editor("printf 'edited' >");
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.set_size((80, 24));
+        editor.start(&mut writer, (0, 3))?;
+        type_str(&mut editor, &mut writer, "value")?;
+
+        let mut writer = Vec::new();
+        editor.action(&mut writer, KeyAction::EditInEditor)?;
+        assert_eq!("edited", editor.buf.buffer());
+        let out = String::from_utf8_lossy(&writer).into_owned();
+        // Redrawn on the row after the suspended prompt
+        assert!(out.contains("\x1b[5;1H\x1b[J> value"));
+        assert!(out.contains("\x1b[5;3Hedited\x1b[K"));
+
+        let options = PromptOptions::new().editor("false");
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "value")?;
+        assert!(editor.action(&mut writer, KeyAction::EditInEditor).is_err());
+        assert_eq!("value", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[test]
+    fn paste_collapse_whitespace() -> Result<()> {
+        let options =
+            PromptOptions::new().paste_whitespace(PasteWhitespace::Collapse);
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.paste(&mut writer, "  a  b  ")?;
+        assert_eq!("a b", editor.buf.buffer());
+
+        let options = PromptOptions::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.paste(&mut writer, "  a\nb  ")?;
+        assert_eq!("  a b  ", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[test]
+    fn on_paste() -> Result<()> {
+        let options = PromptOptions::new().on_paste(|s| s.to_uppercase());
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "a")?;
+        editor.paste(&mut writer, "bc\nd")?;
+        assert_eq!("aBC D", editor.buf.buffer());
+
+        // Newlines returned by the hook are handled like pasted newlines
+        let options = PromptOptions::new().on_paste(|s| s.replace(',', "\n"));
+        let mut editor = Editor::new("> ", &options);
+        editor.paste(&mut writer, "a,b")?;
+        assert_eq!("a b", editor.buf.buffer());
+        Ok(())
+    }
+
+    #[test]
+    fn continue_unbalanced() -> Result<()> {
+        let options = PromptOptions::new().continue_unbalanced(true);
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "print(1,")?;
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!(Flow::Continue, flow);
+        type_str(&mut editor, &mut writer, " \")\")")?;
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!(Flow::Submit(None), flow);
+        assert_eq!("print(1,\n \")\")", editor.buf.buffer());
+
+        let options = PromptOptions::new();
+        let mut editor = Editor::new("> ", &options);
+        type_str(&mut editor, &mut writer, "print(1,")?;
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!(Flow::Submit(None), flow);
+
+        assert!(is_unbalanced("echo 'it\\'s"));
+        assert!(!is_unbalanced("f([1], {'a': \"}\"})"));
+        Ok(())
+    }
+
+    #[test]
+    fn editor_multiline() -> Result<()> {
+        let options = PromptOptions::new().multiline(MultiLine {
+            repeat_prompt: true,
+            ..Default::default()
+        });
+        let mut writer = Vec::new();
+        let mut editor = Editor::new("> ", &options);
+        editor.set_size((80, 24));
+        editor.start(&mut writer, (0, 0))?;
+        type_str(&mut editor, &mut writer, "a")?;
+        let flow = editor.handle_key(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!(Flow::Continue, flow);
+        type_str(&mut editor, &mut writer, "b")?;
+        assert_eq!("a\nb", editor.buf.buffer());
+        let out = String::from_utf8_lossy(&writer).into_owned();
+        assert!(out.contains("\x1b[1;1H\r\n> \x1b[2;3H"));
+        assert!(out.ends_with("\x1b[2;3Hb\x1b[K\x1b[2;4H"));
+        Ok(())
+    }
+}
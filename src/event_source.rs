@@ -0,0 +1,107 @@
+//! Sources of terminal events.
+use anyhow::{bail, Result};
+use crossterm::event::{poll, read, Event, KeyEvent};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Trait for types that supply events to a prompt.
+pub trait EventSource {
+    /// Read the next event, blocking until one is available.
+    fn read(&mut self) -> Result<Event>;
+
+    /// Determine if an event is available within a timeout.
+    ///
+    /// Only used while waiting for completions that run in the
+    /// background, the spinner is redrawn every time no event is
+    /// available; the default implementation returns `false` so
+    /// events are read once the completions are ready.
+    fn poll(&mut self, _timeout: Duration) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+/// Event source that reads events from the terminal.
+///
+/// This is the event source used when none is configured.
+#[derive(Debug, Default)]
+pub struct TerminalEventSource;
+
+impl EventSource for TerminalEventSource {
+    fn read(&mut self) -> Result<Event> {
+        Ok(read()?)
+    }
+
+    fn poll(&mut self, timeout: Duration) -> Result<bool> {
+        Ok(poll(timeout)?)
+    }
+}
+
+/// Event source that supplies a scripted list of events.
+///
+/// Prompts using this event source do not enable raw mode or
+/// query the terminal so they can be used in tests; reading
+/// after all the events have been consumed is an error.
+#[derive(Debug, Default)]
+pub struct VecEventSource {
+    events: VecDeque<Event>,
+}
+
+impl VecEventSource {
+    /// Create a new event source.
+    pub fn new(events: Vec<Event>) -> Self {
+        Self {
+            events: events.into(),
+        }
+    }
+
+    /// Create a new event source from key events.
+    pub fn keys(events: Vec<KeyEvent>) -> Self {
+        Self::new(events.into_iter().map(Event::Key).collect())
+    }
+}
+
+impl EventSource for VecEventSource {
+    fn read(&mut self) -> Result<Event> {
+        match self.events.pop_front() {
+            Some(event) => Ok(event),
+            None => bail!("no more events to read"),
+        }
+    }
+}
+
+/// Event source that records the key events read from
+/// another event source.
+pub(crate) struct Recorder<'s> {
+    source: &'s mut dyn EventSource,
+    events: Option<Vec<KeyEvent>>,
+}
+
+impl<'s> Recorder<'s> {
+    /// Create a recorder, key events are only recorded
+    /// when `record` is set.
+    pub fn new(source: &'s mut dyn EventSource, record: bool) -> Self {
+        Self {
+            source,
+            events: if record { Some(Vec::new()) } else { None },
+        }
+    }
+
+    /// Get the recorded key events.
+    pub fn into_events(self) -> Vec<KeyEvent> {
+        self.events.unwrap_or_default()
+    }
+}
+
+impl EventSource for Recorder<'_> {
+    fn read(&mut self) -> Result<Event> {
+        let event = self.source.read()?;
+        if let (Some(events), Event::Key(event)) = (&mut self.events, &event) {
+            events.push(*event);
+        }
+        Ok(event)
+    }
+
+    fn poll(&mut self, timeout: Duration) -> Result<bool> {
+        self.source.poll(timeout)
+    }
+}
@@ -0,0 +1,74 @@
+//! Edit a value using an external editor program.
+use anyhow::{bail, Result};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counter for unique temporary file names.
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Get the editor command from the environment.
+///
+/// Uses `VISUAL` then `EDITOR` falling back to `vi`.
+pub(crate) fn editor_command() -> String {
+    std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string())
+}
+
+/// Edit a value in an external editor and return the new value.
+///
+/// The command is run using the shell with the path to a
+/// temporary file as the last argument. A single trailing newline
+/// that editors typically add is removed.
+pub(crate) fn edit(command: &str, value: &str) -> Result<String> {
+    let path = std::env::temp_dir().join(format!(
+        "crossterm-prompt-{}-{}.txt",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst),
+    ));
+    std::fs::write(&path, value)?;
+
+    let _guard = scopeguard::guard(&path, |path| {
+        let _ = std::fs::remove_file(path);
+    });
+
+    #[cfg(unix)]
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", command))
+        .arg("sh")
+        .arg(&path)
+        .status()?;
+
+    #[cfg(windows)]
+    let status = Command::new("cmd")
+        .arg("/C")
+        .arg(format!("{} \"{}\"", command, path.display()))
+        .status()?;
+
+    if !status.success() {
+        bail!("editor command `{}` failed: {}", command, status);
+    }
+
+    let mut value = std::fs::read_to_string(&path)?;
+    if value.ends_with('\n') {
+        value.pop();
+        if value.ends_with('\r') {
+            value.pop();
+        }
+    }
+    Ok(value)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_mock_editor() -> Result<()> {
+        let value = edit("printf ' baz\\n' >>", "foo")?;
+        assert_eq!("foo baz", value);
+        assert!(edit("false", "foo").is_err());
+        Ok(())
+    }
+}
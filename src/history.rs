@@ -1,14 +1,23 @@
 //! Support for shell history.
+use anyhow::Result;
+use std::io::{BufRead, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Options for history implementations.
 pub struct HistoryOptions {
     /// Maximum number of history items.
-    maximum_size: u16,
+    pub maximum_size: u16,
+
+    /// Record the time each item was added.
+    pub timestamps: bool,
 }
 
 impl Default for HistoryOptions {
     fn default() -> Self {
-        Self { maximum_size: 1000 }
+        Self {
+            maximum_size: 1000,
+            timestamps: false,
+        }
     }
 }
 
@@ -50,12 +59,20 @@ pub trait History {
 
     /// Move the cursor to the next entry in the history.
     fn next(&mut self) -> Option<&String>;
+
+    /// Get the time the item at an index was added.
+    ///
+    /// The default implementation does not record timestamps.
+    fn timestamp(&self, _index: usize) -> Option<SystemTime> {
+        None
+    }
 }
 
 /// Stores history in memory.
 #[derive(Default)]
 pub struct MemoryHistory {
     items: Vec<String>,
+    timestamps: Vec<Option<SystemTime>>,
     options: HistoryOptions,
     cursor: Option<usize>,
 }
@@ -65,10 +82,72 @@ impl MemoryHistory {
     pub fn new(options: HistoryOptions) -> Self {
         Self {
             items: vec![],
+            timestamps: vec![],
             cursor: None,
             options,
         }
     }
+
+    /// Load history items written by [MemoryHistory::save].
+    ///
+    /// Each line is an item and a line containing `#` followed
+    /// by the seconds since the Unix epoch is the timestamp for
+    /// the next item; items without a timestamp are allowed.
+    pub fn load<R: BufRead>(
+        options: HistoryOptions,
+        reader: R,
+    ) -> Result<Self> {
+        let mut history = Self::new(options);
+        let mut timestamp = None;
+        for line in reader.lines() {
+            let line = line?;
+            if let Some(secs) = parse_timestamp(&line) {
+                timestamp = Some(UNIX_EPOCH + Duration::from_secs(secs));
+            } else {
+                history.insert(line, timestamp.take());
+            }
+        }
+        if !history.items.is_empty() {
+            history.cursor = Some(history.items.len());
+        }
+        Ok(history)
+    }
+
+    /// Write the history items one per line.
+    ///
+    /// Items with a timestamp are preceded by a line containing
+    /// the timestamp like the bash history file format.
+    pub fn save<W: Write>(&self, writer: &mut W) -> Result<()> {
+        for (item, timestamp) in self.items.iter().zip(&self.timestamps) {
+            if let Some(timestamp) = timestamp {
+                let secs = timestamp.duration_since(UNIX_EPOCH)?.as_secs();
+                writeln!(writer, "#{}", secs)?;
+            }
+            writeln!(writer, "{}", item)?;
+        }
+        Ok(())
+    }
+
+    /// Add an item removing the oldest item when full.
+    fn insert(&mut self, item: String, timestamp: Option<SystemTime>) {
+        self.items.push(item);
+        self.timestamps.push(timestamp);
+
+        if self.items.len() > self.options.maximum_size as usize {
+            self.items.remove(0);
+            self.timestamps.remove(0);
+        }
+    }
+}
+
+/// Parse a timestamp line from a history file.
+fn parse_timestamp(line: &str) -> Option<u64> {
+    let secs = line.strip_prefix('#')?;
+    if !secs.is_empty() && secs.bytes().all(|b| b.is_ascii_digit()) {
+        secs.parse().ok()
+    } else {
+        None
+    }
 }
 
 impl History for MemoryHistory {
@@ -98,6 +177,7 @@ impl History for MemoryHistory {
 
     fn clear(&mut self) {
         self.items = vec![];
+        self.timestamps = vec![];
         self.cursor = None;
     }
 
@@ -110,14 +190,23 @@ impl History for MemoryHistory {
     }
 
     fn push(&mut self, item: String) {
-        self.items.push(item);
-
-        if self.items.len() > self.options.maximum_size as usize {
-            self.items.remove(0);
-        }
+        // Timestamps are only stored to the second
+        let timestamp = if self.options.timestamps {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|now| UNIX_EPOCH + Duration::from_secs(now.as_secs()))
+        } else {
+            None
+        };
+        self.insert(item, timestamp);
         self.cursor = Some(self.items.len());
     }
 
+    fn timestamp(&self, index: usize) -> Option<SystemTime> {
+        self.timestamps.get(index).copied().flatten()
+    }
+
     fn previous(&mut self) -> Option<&String> {
         if let Some(cursor) = self.cursor {
             if cursor > 0 {
@@ -138,7 +227,7 @@ impl History for MemoryHistory {
 
     fn next(&mut self) -> Option<&String> {
         if let Some(cursor) = self.cursor {
-            if cursor < self.items.len() - 1 {
+            if cursor + 1 < self.items.len() {
                 self.move_by(1)
             } else {
                 self.cursor = Some(self.items.len());
@@ -152,7 +241,7 @@ impl History for MemoryHistory {
     fn move_by(&mut self, amount: i16) -> Option<&String> {
         if let Some(cursor) = self.cursor {
             let new_pos = if amount.is_negative() {
-                cursor - amount.wrapping_abs() as i16 as usize
+                cursor - amount.unsigned_abs() as usize
             } else {
                 cursor + amount as usize
             };
@@ -184,4 +273,46 @@ mod tests {
         assert_eq!(&Some(2), history.position());
         assert_eq!(None, history.get());
     }
+
+    #[test]
+    fn history_timestamps() -> Result<()> {
+        let options = HistoryOptions {
+            timestamps: true,
+            ..Default::default()
+        };
+        let mut history = MemoryHistory::new(options);
+        history.push("foo".to_string());
+        history.push("bar".to_string());
+        assert!(history.timestamp(0).is_some());
+        assert_eq!(None, history.timestamp(2));
+
+        let mut file = Vec::new();
+        history.save(&mut file)?;
+        let loaded = MemoryHistory::load(Default::default(), &file[..])?;
+        assert_eq!(history.items(), loaded.items());
+        assert_eq!(history.timestamp(0), loaded.timestamp(0));
+        assert_eq!(history.timestamp(1), loaded.timestamp(1));
+
+        // Entries without timestamps and comments
+        let file = "#1700000000\nfoo\nbar\n# note\n";
+        let mut loaded =
+            MemoryHistory::load(Default::default(), file.as_bytes())?;
+        assert_eq!(&vec!["foo", "bar", "# note"], loaded.items());
+        assert_eq!(
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            loaded.timestamp(0)
+        );
+        assert_eq!(None, loaded.timestamp(1));
+        assert_eq!(Some(&"# note".to_string()), loaded.previous());
+        Ok(())
+    }
+
+    #[test]
+    fn load_empty() -> Result<()> {
+        let mut history = MemoryHistory::load(Default::default(), &b""[..])?;
+        assert!(history.items().is_empty());
+        assert_eq!(None, history.next());
+        assert_eq!(None, history.previous());
+        Ok(())
+    }
 }
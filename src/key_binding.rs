@@ -13,6 +13,25 @@ enum KeyType {
     Func,
 }
 
+impl KeyType {
+    /// Get the kind of a key event.
+    fn of(event: &KeyEvent) -> Self {
+        match event.code {
+            KeyCode::Char(_) => {
+                if event.modifiers.intersects(KeyModifiers::CONTROL)
+                    || event.modifiers.intersects(KeyModifiers::ALT)
+                {
+                    KeyType::Named
+                } else {
+                    KeyType::Char
+                }
+            }
+            KeyCode::F(_) => KeyType::Func,
+            _ => KeyType::Named,
+        }
+    }
+}
+
 type KeyActionHandler = Box<dyn Fn(&KeyEvent) -> Vec<KeyAction>>;
 
 /// Definition of a key event with associated actions.
@@ -23,18 +42,25 @@ struct KeyDefinition {
 }
 
 /// Actions that keys may trigger.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum KeyAction {
     /// Write the character to the terminal.
     WriteChar(char),
     /// Submit the line.
     SubmitLine,
+    /// Submit the line with a name for the submit action.
+    ///
+    /// Unlike [KeyAction::SubmitLine] this always submits
+    /// the value, even for multiline input.
+    SubmitAs(&'static str),
     /// Move cursor left.
     MoveCursorLeft,
     /// Move cursor right.
     MoveCursorRight,
     /// Erase the last character.
     EraseCharacter,
+    /// Erase the character under the cursor.
+    EraseCharacterForward,
     /// Clear the screen.
     ClearScreen,
     /// Abort the prompt.
@@ -44,6 +70,31 @@ pub enum KeyAction {
     /// Move to end of the line.
     MoveToLineEnd,
 
+    /// Move to the end of the next word like `M-f` in emacs.
+    ForwardWord,
+    /// Move to the start of the previous word like `M-b` in emacs.
+    BackwardWord,
+
+    /// Move to the blank line before the paragraph like `M-{`
+    /// in emacs.
+    MoveToPreviousParagraph,
+    /// Move to the blank line after the paragraph like `M-}`
+    /// in emacs.
+    MoveToNextParagraph,
+
+    /// Add a digit to the numeric argument for the next action.
+    DigitArgument(u8),
+
+    /// Move to the column of the line given by the numeric argument.
+    ///
+    /// The column is the number of display columns before the
+    /// cursor on the line with tabs expanded to the next tab stop;
+    /// a wide character covering the column is placed after the
+    /// cursor; without an argument the cursor moves to the
+    /// beginning of the line and a column beyond the end of the
+    /// line moves to the end.
+    GoToColumn,
+
     /// Erase to the beginning of the line.
     EraseToLineBegin,
 
@@ -53,6 +104,94 @@ pub enum KeyAction {
     /// Erase the previous word.
     ErasePreviousWord,
 
+    /// Erase back to the previous whitespace regardless
+    /// of punctuation like `unix-word-rubout` in bash.
+    UnixWordRubout,
+
+    /// Erase the spaces and tabs around the cursor.
+    DeleteHorizontalSpace,
+
+    /// Replace the spaces and tabs around the cursor
+    /// with a single space.
+    JustOneSpace,
+
+    /// Erase from the cursor up to but not including the next
+    /// occurrence on the line of the character typed next
+    /// like `dt` in vi.
+    ///
+    /// Rings the bell when the character is not found.
+    DeleteToChar,
+
+    /// Swap the grapheme under the cursor with the grapheme
+    /// after it and move the cursor forward like `xp` in vi.
+    ///
+    /// Does nothing at the end of a line.
+    TransposeForward,
+
+    /// Complete the word before the cursor or insert whitespace.
+    Tab,
+
+    /// Edit the value in an external editor.
+    EditInEditor,
+
+    /// Copy the word under the cursor to the kill ring.
+    CopyWord,
+
+    /// Insert the most recently killed text.
+    Yank,
+
+    /// Rotate the kill ring so the next yank inserts the
+    /// entry killed before the current entry, bound to `Alt+y`.
+    RotateKillRing,
+
+    /// Set the mark at the cursor.
+    SetMark,
+
+    /// Set the mark at the beginning and move the cursor to the end.
+    SelectAll,
+
+    /// Erase the text between the mark and the cursor
+    /// to the kill ring.
+    KillRegion,
+
+    /// Copy the text between the mark and the cursor
+    /// to the kill ring.
+    CopyRegion,
+
+    /// Wrap the text between the mark and the cursor in an
+    /// opening and closing delimiter.
+    ///
+    /// When there is no region an empty pair is inserted with
+    /// the cursor between the delimiters.
+    WrapRegion(char, char),
+
+    /// Add or remove the comment prefix at the beginning
+    /// of the current line.
+    ToggleComment,
+
+    /// Start over with an empty value.
+    ///
+    /// Clears the value and the mark, leaves the history search,
+    /// picker and completion listing, discards pending completions,
+    /// the numeric argument and any partially typed command then
+    /// redraws the prompt. The kill ring and history are kept.
+    ///
+    /// The action takes precedence over the keys handled by the
+    /// history search and picker.
+    ResetLine,
+
+    /// Sort the lines of a multiline value.
+    SortLines,
+
+    /// Remove lines of a multiline value that repeat an
+    /// earlier line.
+    DedupLines,
+
+    /// Toggle masking of a password so it may be shown.
+    ///
+    /// Only applies when password options are configured.
+    ToggleMask,
+
     /// Go to previous history item.
     #[cfg(any(feature = "history", doc))]
     #[doc(cfg(feature = "history"))]
@@ -62,6 +201,84 @@ pub enum KeyAction {
     #[cfg(any(feature = "history", doc))]
     #[doc(cfg(feature = "history"))]
     HistoryNext,
+
+    /// Incremental search backwards through the history.
+    #[cfg(any(feature = "history", doc))]
+    #[doc(cfg(feature = "history"))]
+    ReverseSearch,
+
+    /// Toggle between the two most recent history items.
+    ///
+    /// Shows the most recent item unless it is already
+    /// displayed in which case the item before it is shown.
+    #[cfg(any(feature = "history", doc))]
+    #[doc(cfg(feature = "history"))]
+    HistoryToggle,
+
+    /// Pick a history item from a list filtered by fuzzy matching.
+    ///
+    /// Typing filters the list, the up and down keys change the
+    /// selection, enter places the selected item in the buffer
+    /// and escape restores the buffer; bound to `Alt+p`.
+    #[cfg(any(feature = "history", doc))]
+    #[doc(cfg(feature = "history"))]
+    HistoryPicker,
+
+    /// Insert the current time at the cursor formatted using
+    /// [PromptOptions::timestamp_format](crate::PromptOptions::timestamp_format),
+    /// bound to `F5`.
+    #[cfg(any(feature = "timestamp", doc))]
+    #[doc(cfg(feature = "timestamp"))]
+    InsertTimestamp,
+}
+
+impl KeyAction {
+    /// Determine if this action changes the value.
+    pub(crate) fn is_edit(&self) -> bool {
+        match self {
+            Self::WriteChar(_)
+            | Self::EraseCharacter
+            | Self::EraseCharacterForward
+            | Self::EraseToLineBegin
+            | Self::EraseToLineEnd
+            | Self::ErasePreviousWord
+            | Self::UnixWordRubout
+            | Self::DeleteHorizontalSpace
+            | Self::JustOneSpace
+            | Self::TransposeForward
+            | Self::DeleteToChar
+            | Self::Tab
+            | Self::EditInEditor
+            | Self::Yank
+            | Self::KillRegion
+            | Self::WrapRegion(..)
+            | Self::ToggleComment
+            | Self::SortLines
+            | Self::DedupLines
+            | Self::ResetLine => true,
+            #[cfg(feature = "history")]
+            Self::HistoryPrevious
+            | Self::HistoryNext
+            | Self::ReverseSearch
+            | Self::HistoryToggle
+            | Self::HistoryPicker => true,
+            #[cfg(feature = "timestamp")]
+            Self::InsertTimestamp => true,
+            _ => false,
+        }
+    }
+}
+
+/// Result of intercepting a key event before it is dispatched
+/// to the key bindings.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Intercept {
+    /// Dispatch the key event to the key bindings.
+    Continue,
+    /// Ignore the key event.
+    Consume,
+    /// Perform an action instead of the key bindings.
+    Override(KeyAction),
 }
 
 /// Collection of key bindings.
@@ -70,54 +287,46 @@ pub struct KeyBindings {
 }
 
 impl KeyBindings {
+    /// Bind a key event to a list of actions.
+    ///
+    /// The binding takes precedence over any existing
+    /// binding for the same key event.
+    pub fn bind(mut self, event: KeyEvent, actions: Vec<KeyAction>) -> Self {
+        self.bindings.insert(
+            0,
+            KeyDefinition {
+                kind: KeyType::of(&event),
+                event: Some(event),
+                actions: Box::new(move |_| actions.clone()),
+            },
+        );
+        self
+    }
+
     /// Find the actions for the first key definition
     /// that matches the given key event.
     pub fn first(&self, event: &KeyEvent) -> Option<Vec<KeyAction>> {
-        let kind = match event.code {
-            KeyCode::Char(_) => {
-                if event.modifiers.intersects(KeyModifiers::CONTROL)
-                    || event.modifiers.intersects(KeyModifiers::ALT)
-                {
-                    KeyType::Named
-                } else {
-                    KeyType::Char
-                }
-            }
-            KeyCode::F(_) => KeyType::Func,
-            _ => KeyType::Named,
-        };
-
-        self.bindings.iter().find_map(|d| {
-            if d.kind == kind {
-                match kind {
-                    KeyType::Named => {
-                        if let Some(ev) = &d.event {
-                            if ev == event {
-                                Some((d.actions)(event))
-                            } else {
-                                None
-                            }
-                        } else {
-                            None
-                        }
-                    }
-                    KeyType::Char | KeyType::Func => Some((d.actions)(event)),
-                }
-            } else {
-                None
-            }
+        let kind = KeyType::of(event);
+        self.bindings.iter().find_map(|d| match &d.event {
+            Some(ev) if ev == event => Some((d.actions)(event)),
+            None if d.kind == kind => Some((d.actions)(event)),
+            _ => None,
         })
     }
 }
 
 impl Default for KeyBindings {
     fn default() -> Self {
-        let bindings = vec![
+        let mut bindings = vec![
             // Char(c)
             KeyDefinition {
                 kind: KeyType::Char,
                 event: None,
                 actions: Box::new(|event| match event.code {
+                    // Backspace surfaced as the BS or DEL character
+                    KeyCode::Char('\x08' | '\x7f') => {
+                        vec![KeyAction::EraseCharacter]
+                    }
                     KeyCode::Char(c) => vec![KeyAction::WriteChar(c)],
                     _ => unreachable!(),
                 }),
@@ -158,6 +367,33 @@ impl Default for KeyBindings {
                 }),
                 actions: Box::new(|_| vec![KeyAction::EraseCharacter]),
             },
+            // Ctrl+h, terminals sending BS for backspace
+            KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Char('h'),
+                    modifiers: KeyModifiers::CONTROL,
+                }),
+                actions: Box::new(|_| vec![KeyAction::EraseCharacter]),
+            },
+            // Delete
+            KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Delete,
+                    modifiers: KeyModifiers::NONE,
+                }),
+                actions: Box::new(|_| vec![KeyAction::EraseCharacterForward]),
+            },
+            // Tab
+            KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Tab,
+                    modifiers: KeyModifiers::NONE,
+                }),
+                actions: Box::new(|_| vec![KeyAction::Tab]),
+            },
             #[cfg(any(feature = "history", doc))]
             // Up
             KeyDefinition {
@@ -178,6 +414,36 @@ impl Default for KeyBindings {
                 }),
                 actions: Box::new(|_| vec![KeyAction::HistoryNext]),
             },
+            #[cfg(any(feature = "history", doc))]
+            // Ctrl+r
+            KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Char('r'),
+                    modifiers: KeyModifiers::CONTROL,
+                }),
+                actions: Box::new(|_| vec![KeyAction::ReverseSearch]),
+            },
+            #[cfg(any(feature = "history", doc))]
+            // Alt+p
+            KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Char('p'),
+                    modifiers: KeyModifiers::ALT,
+                }),
+                actions: Box::new(|_| vec![KeyAction::HistoryPicker]),
+            },
+            #[cfg(any(feature = "timestamp", doc))]
+            // F5
+            KeyDefinition {
+                kind: KeyType::Func,
+                event: Some(KeyEvent {
+                    code: KeyCode::F(5),
+                    modifiers: KeyModifiers::NONE,
+                }),
+                actions: Box::new(|_| vec![KeyAction::InsertTimestamp]),
+            },
             // Ctrl+c
             KeyDefinition {
                 kind: KeyType::Named,
@@ -248,10 +514,191 @@ impl Default for KeyBindings {
                     code: KeyCode::Char('w'),
                     modifiers: KeyModifiers::CONTROL,
                 }),
+                actions: Box::new(|_| vec![KeyAction::UnixWordRubout]),
+            },
+            // Alt+Backspace
+            KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Backspace,
+                    modifiers: KeyModifiers::ALT,
+                }),
                 actions: Box::new(|_| vec![KeyAction::ErasePreviousWord]),
             },
+            // Ctrl+Space
+            KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Char(' '),
+                    modifiers: KeyModifiers::CONTROL,
+                }),
+                actions: Box::new(|_| vec![KeyAction::SetMark]),
+            },
+            // Alt+w
+            KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Char('w'),
+                    modifiers: KeyModifiers::ALT,
+                }),
+                actions: Box::new(|_| vec![KeyAction::CopyRegion]),
+            },
+            // Ctrl+y
+            KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Char('y'),
+                    modifiers: KeyModifiers::CONTROL,
+                }),
+                actions: Box::new(|_| vec![KeyAction::Yank]),
+            },
+            // Alt+y
+            KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Char('y'),
+                    modifiers: KeyModifiers::ALT,
+                }),
+                actions: Box::new(|_| vec![KeyAction::RotateKillRing]),
+            },
+            // Alt+f
+            KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Char('f'),
+                    modifiers: KeyModifiers::ALT,
+                }),
+                actions: Box::new(|_| vec![KeyAction::ForwardWord]),
+            },
+            // Alt+b
+            KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Char('b'),
+                    modifiers: KeyModifiers::ALT,
+                }),
+                actions: Box::new(|_| vec![KeyAction::BackwardWord]),
+            },
+            // Alt+{
+            KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Char('{'),
+                    modifiers: KeyModifiers::ALT,
+                }),
+                actions: Box::new(|_| vec![KeyAction::MoveToPreviousParagraph]),
+            },
+            // Alt+}
+            KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Char('}'),
+                    modifiers: KeyModifiers::ALT,
+                }),
+                actions: Box::new(|_| vec![KeyAction::MoveToNextParagraph]),
+            },
+            // Alt+\
+            KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Char('\\'),
+                    modifiers: KeyModifiers::ALT,
+                }),
+                actions: Box::new(|_| vec![KeyAction::DeleteHorizontalSpace]),
+            },
+            // Alt+Space
+            KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Char(' '),
+                    modifiers: KeyModifiers::ALT,
+                }),
+                actions: Box::new(|_| vec![KeyAction::JustOneSpace]),
+            },
         ];
 
+        // Alt+0 to Alt+9
+        for digit in 0..10u8 {
+            bindings.push(KeyDefinition {
+                kind: KeyType::Named,
+                event: Some(KeyEvent {
+                    code: KeyCode::Char((b'0' + digit) as char),
+                    modifiers: KeyModifiers::ALT,
+                }),
+                actions: Box::new(move |_| {
+                    vec![KeyAction::DigitArgument(digit)]
+                }),
+            });
+        }
+
         Self { bindings }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind_overrides_default() {
+        let ctrl_w = KeyEvent {
+            code: KeyCode::Char('w'),
+            modifiers: KeyModifiers::CONTROL,
+        };
+        let f1 = KeyEvent {
+            code: KeyCode::F(1),
+            modifiers: KeyModifiers::NONE,
+        };
+        let bindings = KeyBindings::default()
+            .bind(ctrl_w, vec![KeyAction::CopyWord])
+            .bind(f1, vec![KeyAction::Yank]);
+        assert_eq!(Some(vec![KeyAction::CopyWord]), bindings.first(&ctrl_w));
+        assert_eq!(Some(vec![KeyAction::Yank]), bindings.first(&f1));
+        let f2 = KeyEvent {
+            code: KeyCode::F(2),
+            modifiers: KeyModifiers::NONE,
+        };
+        assert_eq!(None, bindings.first(&f2));
+    }
+
+    #[test]
+    fn backspace_variations() {
+        let bindings = KeyBindings::default();
+        let events = [
+            KeyEvent::from(KeyCode::Backspace),
+            KeyEvent::new(KeyCode::Char('h'), KeyModifiers::CONTROL),
+            KeyEvent::from(KeyCode::Char('\x08')),
+            KeyEvent::from(KeyCode::Char('\x7f')),
+        ];
+        for event in events.iter() {
+            assert_eq!(
+                Some(vec![KeyAction::EraseCharacter]),
+                bindings.first(event)
+            );
+        }
+        assert_eq!(
+            Some(vec![KeyAction::EraseCharacterForward]),
+            bindings.first(&KeyEvent::from(KeyCode::Delete))
+        );
+    }
+
+    #[test]
+    fn default_bindings() {
+        let bindings = KeyBindings::default();
+        let alt = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::ALT);
+        assert_eq!(
+            Some(vec![KeyAction::RotateKillRing]),
+            bindings.first(&alt('y'))
+        );
+        #[cfg(feature = "history")]
+        assert_eq!(
+            Some(vec![KeyAction::HistoryPicker]),
+            bindings.first(&alt('p'))
+        );
+        #[cfg(feature = "timestamp")]
+        assert_eq!(
+            Some(vec![KeyAction::InsertTimestamp]),
+            bindings.first(&KeyEvent::from(KeyCode::F(5)))
+        );
+    }
+}
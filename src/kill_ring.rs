@@ -0,0 +1,82 @@
+//! Ring of killed text that may be yanked back into the buffer.
+use std::collections::VecDeque;
+
+/// Default maximum number of entries.
+const CAPACITY: usize = 60;
+
+/// Stores killed text with the most recent entry first.
+pub struct KillRing {
+    entries: VecDeque<String>,
+    capacity: usize,
+}
+
+impl Default for KillRing {
+    fn default() -> Self {
+        Self::new(CAPACITY)
+    }
+}
+
+impl KillRing {
+    /// Create a kill ring with a maximum number of entries.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Push text onto the ring, empty text is ignored.
+    pub fn push(&mut self, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        self.entries.push_front(text);
+        self.entries.truncate(self.capacity);
+    }
+
+    /// Get the entry to yank.
+    pub fn yank(&self) -> Option<&str> {
+        self.entries.front().map(|s| &s[..])
+    }
+
+    /// Rotate the ring so the entry after the current
+    /// entry is yanked next.
+    pub fn rotate(&mut self) {
+        if !self.entries.is_empty() {
+            self.entries.rotate_left(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kill_ring_capacity() {
+        let mut ring = KillRing::new(2);
+        assert_eq!(None, ring.yank());
+        ring.push("foo".to_string());
+        ring.push(String::new());
+        assert_eq!(Some("foo"), ring.yank());
+        ring.push("bar".to_string());
+        ring.push("baz".to_string());
+        assert_eq!(Some("baz"), ring.yank());
+        assert_eq!(2, ring.entries.len());
+    }
+
+    #[test]
+    fn kill_ring_rotate() {
+        let mut ring = KillRing::default();
+        ring.rotate();
+        ring.push("foo".to_string());
+        ring.push("bar".to_string());
+        ring.push("baz".to_string());
+        ring.rotate();
+        assert_eq!(Some("bar"), ring.yank());
+        ring.rotate();
+        assert_eq!(Some("foo"), ring.yank());
+        ring.rotate();
+        assert_eq!(Some("baz"), ring.yank());
+    }
+}
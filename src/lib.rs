@@ -3,19 +3,39 @@
 #![feature(thread_id_value)]
 
 //! Prompt library for crossterm.
+//!
+//! The terminal does not report pasted text as a paste so
+//! [prompt] and the other blocking functions handle pasted
+//! text as typed keys; the paste options only apply to text
+//! given to [Prompt::paste] by a caller that owns the event loop.
 use anyhow::{bail, Result};
 use crossterm::{
     cursor,
-    event::{read, Event},
-    terminal::{disable_raw_mode, enable_raw_mode, size, Clear, ClearType},
-    ExecutableCommand, QueueableCommand,
+    event::KeyEvent,
+    terminal::{disable_raw_mode, enable_raw_mode, size},
 };
 use std::borrow::Cow;
 use std::error::Error;
 use std::io::Write;
+use std::time::Duration;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
+mod editor;
+mod event_source;
+mod external;
 mod key_binding;
+mod kill_ring;
 mod options;
+mod paste;
+mod prefix;
+mod prompt;
+
+#[cfg(feature = "history")]
+mod picker;
+
+#[cfg(feature = "history")]
+mod search;
 
 #[cfg(any(feature = "panic", doc))]
 #[doc(cfg(feature = "panic"))]
@@ -26,9 +46,19 @@ pub use panic::{stderr_panic_hook, stdout_panic_hook};
 
 mod terminal_buffer;
 
+#[cfg(feature = "timestamp")]
+mod timestamp;
+
+use event_source::Recorder;
+pub use event_source::*;
 pub use key_binding::*;
 pub use options::*;
-use terminal_buffer::TerminalBuffer;
+pub use prefix::{AsPrefix, Prefix};
+pub use prompt::{Prompt, PromptOutcome};
+
+#[cfg(any(feature = "completion", doc))]
+#[doc(cfg(feature = "completion"))]
+pub mod completion;
 
 #[cfg(any(feature = "history", doc))]
 #[doc(cfg(feature = "history"))]
@@ -58,49 +88,111 @@ where
     }
 }
 
+/// Value for a prompt and the action that submitted it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Response {
+    /// The value entered.
+    pub value: String,
+    /// Name of the action that submitted the value.
+    ///
+    /// This is `None` when the value was submitted using
+    /// [KeyAction::SubmitLine] or the prompt was aborted.
+    pub submit: Option<&'static str>,
+    /// Whether the prompt was left to go back to a previous prompt.
+    ///
+    /// See [EmptyBackspace::Back].
+    pub back: bool,
+    /// Key events read by the prompt.
+    ///
+    /// Only available when [PromptOptions::record_events] is set,
+    /// the events may be replayed using a [VecEventSource].
+    pub events: Vec<KeyEvent>,
+    /// Whether the prompt wrapped to more than one row
+    /// when it was submitted.
+    pub wrapped: bool,
+    /// The prefix and value as they were displayed when the
+    /// value was submitted, styling is removed and wrapped
+    /// rows are separated by newlines.
+    ///
+    /// Masked values contain the mask characters.
+    pub visible: String,
+}
+
+impl Response {
+    /// Length of the value in bytes when encoded as UTF-8.
+    pub fn byte_len(&self) -> usize {
+        self.value.len()
+    }
+
+    /// Number of graphemes in the value.
+    pub fn grapheme_count(&self) -> usize {
+        self.value.graphemes(true).count()
+    }
+
+    /// Number of columns used to display the value.
+    pub fn columns(&self) -> usize {
+        UnicodeWidthStr::width(&self.value[..])
+    }
+}
+
 /// Show a prompt.
-pub fn prompt<'a, S: AsRef<str>, W>(
+pub fn prompt<S: AsPrefix, W>(
     prefix: S,
-    writer: &'a mut W,
+    writer: &mut W,
     options: &PromptOptions,
 ) -> Result<String>
 where
     W: Write,
 {
-    if prefix.as_ref().len() > u16::MAX as usize {
+    Ok(prompt_response(prefix, writer, options)?.value)
+}
+
+/// Show a prompt and get the response including the
+/// action that submitted the value.
+pub fn prompt_response<S: AsPrefix, W>(
+    prefix: S,
+    writer: &mut W,
+    options: &PromptOptions,
+) -> Result<Response>
+where
+    W: Write,
+{
+    let prefix = prefix.as_prefix();
+    if prefix.as_str().len() > u16::MAX as usize {
         bail!("prompt prefix is too long");
     }
 
-    let value = if let Some(required) = &options.required {
-        let mut value;
+    let response = if let Some(required) = &options.required {
+        let mut response;
         let mut attempts = 0u16;
         loop {
-            value = validate(prefix.as_ref(), writer, options)?;
+            response = validate(&prefix, writer, options)?;
             let check_value = if required.trim {
-                value.trim()
+                response.value.trim()
             } else {
-                &value[..]
+                &response.value[..]
             };
             attempts += 1;
-            if !check_value.is_empty()
+            if response.back
+                || !check_value.is_empty()
                 || (required.max_attempts > 0
                     && attempts >= required.max_attempts)
             {
                 break;
             }
         }
-        value
+        response
     } else {
-        validate(prefix.as_ref(), writer, options)?
+        validate(&prefix, writer, options)?
     };
 
-    Ok(value)
+    Ok(response)
 }
 
 /// Show a prompt and parse the value to another type.
-pub fn parse<'a, T, W, S: AsRef<str>>(
+pub fn parse<T, W, S: AsPrefix>(
     prefix: S,
-    writer: &'a mut W,
+    writer: &mut W,
     options: &PromptOptions,
 ) -> Result<T>
 where
@@ -108,229 +200,445 @@ where
     <T as std::str::FromStr>::Err: Error + Sync + Send + 'static,
     W: Write,
 {
-    let value: String = prompt(prefix.as_ref(), writer, options)?;
-    let value: T = (&value[..]).parse::<T>()?;
+    let value: String = prompt(prefix, writer, options)?;
+    let value: T = value[..].parse::<T>()?;
     Ok(value)
 }
 
-fn validate<'a, S: AsRef<str>, W>(
-    prefix: S,
-    writer: &'a mut W,
+fn validate<W>(
+    prefix: &Prefix,
+    writer: &mut W,
     options: &PromptOptions,
-) -> Result<String>
+) -> Result<Response>
 where
     W: Write,
 {
-    let mut value = if let Some(validation) = &options.validation {
-        let value = run(prefix.as_ref(), writer, options)?;
-        if (validation.validate)(&value) {
-            value
-        } else {
-            validate(prefix.as_ref(), writer, options)?
+    let mut response = loop {
+        let mut response = run(prefix, writer, options)?;
+        if response.back {
+            break response;
         }
-    } else {
-        run(prefix.as_ref(), writer, options)?
+        if options.check(&response.value) != Validity::Valid {
+            continue;
+        }
+        if let Some(allowed) = &options.allowed {
+            match allowed.find(&response.value) {
+                Some(value) => response.value = value.to_string(),
+                None => {
+                    writer.write_all(allowed.message.as_bytes())?;
+                    writer.write_all(b"\r\n")?;
+                    continue;
+                }
+            }
+        }
+        break response;
     };
 
     if let Some(transformer) = &options.transformer {
-        value = match (transformer.transform)(&value) {
-            Cow::Borrowed(_) => value,
+        response.value = match (transformer.transform)(&response.value) {
+            Cow::Borrowed(_) => response.value,
             Cow::Owned(s) => s,
         }
     }
 
-    Ok(value)
+    Ok(response)
 }
 
-fn run<'a, S: AsRef<str>, W>(
-    prefix: S,
-    writer: &'a mut W,
+/// Interval for redrawing while waiting for completions.
+const TICK: Duration = Duration::from_millis(100);
+
+fn run<W>(
+    prefix: &Prefix,
+    writer: &mut W,
     options: &PromptOptions,
-) -> Result<String>
+) -> Result<Response>
 where
     W: Write,
 {
-    enable_raw_mode()?;
+    let mut terminal = TerminalEventSource;
+    let mut scripted = options
+        .event_source
+        .as_ref()
+        .map(|source| source.lock().unwrap());
+    let source: &mut dyn EventSource = match scripted.as_mut() {
+        Some(source) => &mut ***source,
+        None => &mut terminal,
+    };
+    let mut source = Recorder::new(source, options.record_events);
 
-    let _guard = scopeguard::guard((), |_| {
-        let _ = disable_raw_mode();
-    });
+    let mut prompt = Prompt::new(prefix, options);
 
-    let echo = if let Some(password) = &options.password {
-        password.echo
+    // Scripted events do not use the terminal
+    let _guard = if options.event_source.is_none() {
+        enable_raw_mode()?;
+        if options.size.is_none() {
+            prompt.set_size(size()?);
+        }
+        Some(scopeguard::guard((), |_| {
+            let _ = disable_raw_mode();
+        }))
     } else {
         None
     };
-    let mut buf = TerminalBuffer::new(prefix.as_ref(), echo);
-
-    #[cfg(feature = "history")]
-    let mut history_buffer = String::new();
 
     // Write the initial prefix
-    buf.write_prefix(writer)?;
-
-    'prompt: loop {
-        let (width, height) = size()?;
-        let (column, row) = cursor::position()?;
-
-        buf.set_size((width, height));
-        buf.set_position((column, row));
-
-        match read()? {
-            Event::Key(event) => {
-                if let Some(actions) = options.bindings.first(&event) {
-                    for action in actions {
-                        match action {
-                            KeyAction::WriteChar(c) => {
-                                buf.write_char(writer, c)?;
-                            }
-                            KeyAction::SubmitLine => {
-                                if let Some(multiline) = &options.multiline {
-                                    buf.push(writer, '\n')?;
-                                    writer
-                                        .execute(cursor::MoveTo(0, row + 1))?;
-                                    if multiline.repeat_prompt {
-                                        buf.write_prefix(writer)?;
-                                    } else {
-                                        writer.execute(Clear(
-                                            ClearType::CurrentLine,
-                                        ))?;
-                                    }
-                                } else {
-                                    #[cfg(feature = "history")]
-                                    if let Some(history) = &options.history {
-                                        let mut writer =
-                                            history.lock().unwrap();
-                                        writer.push(buf.buffer().to_string());
-                                    }
-
-                                    if row == height - 1 {
-                                        write!(writer, "{}", '\n')?;
-                                        writer
-                                            .execute(cursor::MoveTo(0, row))?;
-                                    } else {
-                                        writer.execute(
-                                            cursor::MoveToNextLine(1),
-                                        )?;
-                                    }
-
-                                    break 'prompt;
-                                }
-                            }
-                            KeyAction::MoveCursorLeft => {
-                                if column as usize > buf.prefix_columns() {
-                                    writer.execute(cursor::MoveTo(
-                                        column - 1,
-                                        row,
-                                    ))?;
-                                }
-                            }
-                            KeyAction::MoveCursorRight => {
-                                let position = buf.end_pos(buf.buffer());
-
-                                if column < position.0 {
-                                    writer.execute(cursor::MoveTo(
-                                        column + 1,
-                                        row,
-                                    ))?;
-                                }
-                            }
-                            KeyAction::EraseCharacter => {
-                                buf.erase_before(writer, 1)?;
-                            }
-                            KeyAction::AbortPrompt => {
-                                writer.execute(cursor::MoveToNextLine(1))?;
-                                break 'prompt;
-                            }
-                            KeyAction::ClearScreen => {
-                                writer.queue(Clear(ClearType::All))?;
-                                writer.queue(cursor::MoveTo(0, 0))?;
-                                buf.write_prefix(writer)?;
-                            }
-                            KeyAction::MoveToLineBegin => {
-                                writer.execute(cursor::MoveTo(
-                                    buf.prefix_columns().try_into()?,
-                                    row,
-                                ))?;
-                            }
-                            KeyAction::MoveToLineEnd => {
-                                let position = buf.end_pos(buf.buffer());
-                                writer
-                                    .execute(cursor::MoveTo(position.0, row))?;
-                            }
-                            KeyAction::EraseToLineBegin => {
-                                if (column as usize) > buf.prefix_columns() {
-                                    let amount =
-                                        column as usize - buf.prefix_columns();
-                                    buf.erase_before(writer, amount as usize)?;
-                                }
-                            }
-                            KeyAction::EraseToLineEnd => {
-                                if (column as usize) < buf.columns() {
-                                    let amount =
-                                        buf.columns() - (column as usize);
-                                    buf.erase_after(writer, amount as usize)?;
-                                }
-                            }
-                            KeyAction::ErasePreviousWord => {
-                                buf.erase_word_before(writer)?;
-                            }
-                            #[cfg(feature = "history")]
-                            KeyAction::HistoryPrevious => {
-                                if let Some(history) = &options.history {
-                                    let mut history = history.lock().unwrap();
-
-                                    if history.is_last() {
-                                        history_buffer =
-                                            buf.buffer().to_string();
-                                    }
-
-                                    if let Some(history_line) =
-                                        history.previous()
-                                    {
-                                        let position =
-                                            buf.end_pos(&history_line);
-
-                                        buf.refresh(
-                                            writer,
-                                            history_line,
-                                            position,
-                                        )?;
-                                    }
-                                }
-                            }
-                            #[cfg(feature = "history")]
-                            KeyAction::HistoryNext => {
-                                if let Some(history) = &options.history {
-                                    let mut history = history.lock().unwrap();
-                                    if let Some(history_line) = history.next() {
-                                        let position =
-                                            buf.end_pos(&history_line);
-                                        buf.refresh(
-                                            writer,
-                                            history_line,
-                                            position,
-                                        )?;
-                                    } else {
-                                        let position =
-                                            buf.end_pos(&history_buffer);
-
-                                        buf.refresh(
-                                            writer,
-                                            &history_buffer,
-                                            position,
-                                        )?;
-                                    }
-                                }
-                            }
-                        }
-                    }
+    let position = if options.event_source.is_none() {
+        cursor::position()?
+    } else {
+        (0, 0)
+    };
+    prompt.render(writer, position)?;
+
+    let outcome = loop {
+        // Redraw the spinner until completions are ready
+        if prompt.is_waiting() {
+            prompt.tick(writer)?;
+            if prompt.is_waiting() && !source.poll(TICK)? {
+                continue;
+            }
+        }
+        let event = source.read()?;
+        if let Some(outcome) = prompt.handle_event(writer, &event)? {
+            break outcome;
+        }
+    };
+
+    let mut response = outcome.into_response();
+    response.events = source.into_events();
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{Event, KeyCode, KeyModifiers};
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn record_events() -> Result<()> {
+        let events = vec![
+            key(KeyCode::Char('h')),
+            key(KeyCode::Char('i')),
+            key(KeyCode::Left),
+            key(KeyCode::Char('x')),
+            key(KeyCode::Enter),
+        ];
+        let options = PromptOptions::new()
+            .event_source(Box::new(VecEventSource::keys(events.clone())))
+            .record_events(true);
+        let mut writer = Vec::new();
+        let response = prompt_response("> ", &mut writer, &options)?;
+        assert_eq!("hxi", response.value);
+        assert_eq!(events, response.events);
+        assert!(!response.wrapped);
+
+        // Replay the recorded events
+        let options = PromptOptions::new()
+            .event_source(Box::new(VecEventSource::keys(response.events)));
+        let replayed = prompt_response("> ", &mut writer, &options)?;
+        assert_eq!("hxi", replayed.value);
+        assert!(replayed.events.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn response_lengths() -> Result<()> {
+        let events = "日本e\u{301}x"
+            .chars()
+            .map(|c| key(KeyCode::Char(c)))
+            .chain(std::iter::once(key(KeyCode::Enter)))
+            .collect();
+        let options = PromptOptions::new()
+            .event_source(Box::new(VecEventSource::keys(events)));
+        let response = prompt_response("> ", &mut Vec::new(), &options)?;
+        assert_eq!("日本e\u{301}x", response.value);
+        assert_eq!(10, response.byte_len());
+        assert_eq!(4, response.grapheme_count());
+        assert_eq!(6, response.columns());
+        Ok(())
+    }
+
+    #[test]
+    fn validation_incomplete() -> Result<()> {
+        let events = "(1 +"
+            .chars()
+            .chain(std::iter::once('\r'))
+            .chain(" 2)\r".chars())
+            .map(|c| match c {
+                '\r' => key(KeyCode::Enter),
+                c => key(KeyCode::Char(c)),
+            })
+            .collect();
+        let options = PromptOptions::new()
+            .validity(|s| {
+                if s.matches('(').count() > s.matches(')').count() {
+                    Validity::Incomplete
+                } else {
+                    Validity::Valid
                 }
+            })
+            .event_source(Box::new(VecEventSource::keys(events)));
+        let value = prompt("> ", &mut Vec::new(), &options)?;
+        assert_eq!("(1 +\n 2)", value);
+
+        // Complete input submits on the first line
+        let events = vec![key(KeyCode::Char('1')), key(KeyCode::Enter)];
+        let options = PromptOptions::new()
+            .validation(Validation {
+                validate: Box::new(|s| !s.contains('(')),
+            })
+            .validity(|s| (!s.contains('[')).into())
+            .event_source(Box::new(VecEventSource::keys(events)));
+        assert_eq!("1", prompt("> ", &mut Vec::new(), &options)?);
+        Ok(())
+    }
+
+    #[test]
+    fn allowed_values() -> Result<()> {
+        let events = "y\ryes\r"
+            .chars()
+            .map(|c| match c {
+                '\r' => key(KeyCode::Enter),
+                c => key(KeyCode::Char(c)),
+            })
+            .collect();
+        let options = PromptOptions::new()
+            .allowed(Allowed::new(&["yes", "no", "maybe"]))
+            .event_source(Box::new(VecEventSource::keys(events)));
+        let mut writer = Vec::new();
+        assert_eq!("yes", prompt("> ", &mut writer, &options)?);
+        let out = String::from_utf8_lossy(&writer).into_owned();
+        assert_eq!(
+            1,
+            out.matches("value must be one of: yes, no, maybe").count()
+        );
+
+        // Case is ignored and the allowed value is accepted
+        let events = "Y\rMAYBE\r"
+            .chars()
+            .map(|c| match c {
+                '\r' => key(KeyCode::Enter),
+                c => key(KeyCode::Char(c)),
+            })
+            .collect();
+        let options = PromptOptions::new()
+            .allowed(Allowed {
+                case_sensitive: false,
+                ..Allowed::new(&["yes", "no", "maybe"])
+            })
+            .event_source(Box::new(VecEventSource::keys(events)));
+        assert_eq!("maybe", prompt("> ", &mut Vec::new(), &options)?);
+        Ok(())
+    }
+
+    #[cfg(feature = "completion")]
+    #[test]
+    fn allowed_completion() -> Result<()> {
+        let events = vec![
+            key(KeyCode::Char('M')),
+            key(KeyCode::Tab),
+            key(KeyCode::Enter),
+        ];
+        let options = PromptOptions::new()
+            .allowed(Allowed {
+                case_sensitive: false,
+                ..Allowed::new(&["yes", "no", "maybe"])
+            })
+            .event_source(Box::new(VecEventSource::keys(events.clone())));
+        assert_eq!("maybe", prompt("> ", &mut Vec::new(), &options)?);
+
+        // A configured completer is used instead of the values
+        let words = vec!["Mo".to_string()];
+        let options = PromptOptions::new()
+            .completer(Box::new(completion::WordCompleter::new(words)))
+            .allowed(Allowed::new(&["Ma", "Mo"]))
+            .event_source(Box::new(VecEventSource::keys(events)));
+        assert_eq!("Mo", prompt("> ", &mut Vec::new(), &options)?);
+        Ok(())
+    }
+
+    #[test]
+    fn response_visible() -> Result<()> {
+        let events = vec![
+            key(KeyCode::Char('a')),
+            key(KeyCode::Char('b')),
+            key(KeyCode::Enter),
+        ];
+        let options = PromptOptions::new()
+            .event_source(Box::new(VecEventSource::keys(events.clone())));
+        let response = prompt_response("> ", &mut Vec::new(), &options)?;
+        assert_eq!("> ab", response.visible);
+
+        let options = PromptOptions::new()
+            .password(Default::default())
+            .event_source(Box::new(VecEventSource::keys(events)));
+        let response = prompt_response("> ", &mut Vec::new(), &options)?;
+        assert_eq!("ab", response.value);
+        assert_eq!("> **", response.visible);
+        Ok(())
+    }
+
+    #[test]
+    fn change_sender() -> Result<()> {
+        let events = vec![
+            key(KeyCode::Char('a')),
+            key(KeyCode::Char('b')),
+            key(KeyCode::Left),
+            key(KeyCode::Backspace),
+            key(KeyCode::Enter),
+        ];
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let options = PromptOptions::new()
+            .change_sender(sender)
+            .event_source(Box::new(VecEventSource::keys(events)));
+        assert_eq!("b", prompt("> ", &mut Vec::new(), &options)?);
+        drop(options);
+        let values: Vec<String> = receiver.iter().collect();
+        assert_eq!(vec!["a", "ab", "b"], values);
+        Ok(())
+    }
+
+    #[test]
+    fn prepared_prefix() -> Result<()> {
+        fn read<S: AsPrefix>(prefix: S) -> Result<Vec<u8>> {
+            let events = vec![key(KeyCode::Char('日')), key(KeyCode::Enter)];
+            let options = PromptOptions::new()
+                .event_source(Box::new(VecEventSource::keys(events)));
+            let mut writer = Vec::new();
+            assert_eq!("日", prompt(prefix, &mut writer, &options)?);
+            Ok(writer)
+        }
+
+        let expected = read("日本> ")?;
+        let prefix = Prefix::new("日本> ");
+        assert_eq!(6, prefix.columns());
+        for _ in 0..2 {
+            assert_eq!(expected, read(&prefix)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn queued_keys_are_typed() -> Result<()> {
+        let events = vec![
+            key(KeyCode::Char('a')),
+            key(KeyCode::Tab),
+            key(KeyCode::Char('b')),
+            key(KeyCode::Enter),
+            key(KeyCode::Char('c')),
+        ];
+        let options = PromptOptions::new()
+            .paste_whitespace(PasteWhitespace::Collapse)
+            .event_source(Box::new(VecEventSource::keys(events)));
+        let response = prompt_response("> ", &mut Vec::new(), &options)?;
+        assert_eq!("ab", response.value);
+        Ok(())
+    }
+
+    #[test]
+    fn fixed_size() -> Result<()> {
+        let mut events: Vec<Event> = "abcdefghij"
+            .chars()
+            .cycle()
+            .take(45)
+            .map(|c| Event::Key(key(KeyCode::Char(c))))
+            .collect();
+        events.push(Event::Resize(80, 24));
+        events.push(Event::Key(key(KeyCode::Enter)));
+        let options = PromptOptions::new()
+            .size((40, 10))
+            .event_source(Box::new(VecEventSource::new(events)));
+        let mut writer = Vec::new();
+        let response = prompt_response("> ", &mut writer, &options)?;
+        assert_eq!(45, response.value.len());
+        assert!(response.wrapped);
+
+        // The 39th character is the first on the second row
+        let out = String::from_utf8_lossy(&writer).into_owned();
+        assert!(out.contains("\x1b[2;1Hi\x1b[K\x1b[2;2H"));
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_completion() -> Result<()> {
+        use crate::completion::{AsyncCompleter, PendingCompletion};
+        use std::sync::mpsc::channel;
+
+        struct ThreadCompleter;
+        impl AsyncCompleter for ThreadCompleter {
+            fn complete(
+                &self,
+                line: &str,
+                position: usize,
+            ) -> PendingCompletion {
+                let (sender, receiver) = channel();
+                let word = line[..position].to_string();
+                std::thread::spawn(move || {
+                    let _ =
+                        sender.send((0, vec![format!("{}ed", word).into()]));
+                });
+                receiver
             }
-            Event::Mouse(_event) => {}
-            Event::Resize(_width, _height) => {}
         }
+
+        let events = vec![
+            key(KeyCode::Char('a')),
+            key(KeyCode::Tab),
+            key(KeyCode::Enter),
+        ];
+        let options = PromptOptions::new()
+            .async_completer(Box::new(ThreadCompleter))
+            .event_source(Box::new(VecEventSource::keys(events)));
+        let mut writer = Vec::new();
+        let value = prompt("> ", &mut writer, &options)?;
+        assert_eq!("aed", value);
+        assert!(String::from_utf8_lossy(&writer).contains("| completing"));
+        Ok(())
     }
 
-    Ok(buf.into())
+    #[test]
+    fn multiline_line_endings() -> Result<()> {
+        let run = |multiline: MultiLine| -> Result<String> {
+            let events = vec![
+                key(KeyCode::Char('a')),
+                key(KeyCode::Enter),
+                key(KeyCode::Char('b')),
+                key(KeyCode::Enter),
+                KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL),
+            ];
+            let options = PromptOptions::new()
+                .multiline(multiline)
+                .event_source(Box::new(VecEventSource::keys(events)));
+            prompt("> ", &mut Vec::new(), &options)
+        };
+
+        assert_eq!("a\nb\n", run(Default::default())?);
+        assert_eq!(
+            "a\r\nb\r\n",
+            run(MultiLine {
+                line_ending: LineEnding::CrLf,
+                ..Default::default()
+            })?
+        );
+        assert_eq!(
+            "a\r\nb",
+            run(MultiLine {
+                line_ending: LineEnding::CrLf,
+                trim_trailing_newline: true,
+                ..Default::default()
+            })?
+        );
+        assert_eq!(
+            "a\nb",
+            run(MultiLine {
+                trim_trailing_newline: true,
+                ..Default::default()
+            })?
+        );
+        Ok(())
+    }
 }
@@ -1,12 +1,38 @@
 //! Options for creating prompts.
-use crate::key_binding::KeyBindings;
+use crate::key_binding::{Intercept, KeyAction, KeyBindings};
+use crate::EventSource;
+use crossterm::{
+    event::KeyEvent,
+    style::{Color, ContentStyle},
+};
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{mpsc::Sender, Mutex};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
-#[cfg(feature = "history")]
+#[cfg(any(feature = "completion", doc))]
+use crate::completion::Completer;
+
+#[cfg(any(feature = "async", doc))]
+use crate::completion::AsyncCompleter;
+
+#[cfg(any(feature = "history", doc))]
 use crate::history::History;
 
-#[cfg(feature = "history")]
-use std::sync::Mutex;
+#[cfg(any(feature = "timestamp", doc))]
+use std::time::SystemTime;
+
+type KeyHook = Box<dyn FnMut(&KeyEvent) -> Intercept>;
+
+type PasteHook = Box<dyn FnMut(String) -> String>;
+
+type ActionPredicate = Box<dyn FnMut(&KeyAction, &str) -> bool>;
+
+type ValidityHandler = Box<dyn Fn(&str) -> Validity>;
+
+#[cfg(any(feature = "completion", doc))]
+type CompletionHook = Box<dyn FnMut(&str)>;
 
 /// The options to use when creating a prompt.
 #[derive(Default)]
@@ -28,9 +54,127 @@ pub struct PromptOptions {
     /// Options for validating the input.
     pub(crate) validation: Option<Validation>,
 
+    /// Validation that may report the input as incomplete.
+    pub(crate) validity: Option<ValidityHandler>,
+
+    /// Set of values that may be submitted.
+    pub(crate) allowed: Option<Allowed>,
+
+    /// Colors for the submitted line.
+    pub(crate) accept_colors: Option<AcceptColors>,
+
+    /// Styles for the parts of the prompt.
+    pub(crate) theme: Theme,
+
+    /// Frame drawn around the prompt.
+    pub(crate) border: Option<Border>,
+
+    /// Counter for the length of the value.
+    pub(crate) counter: Option<Counter>,
+
+    /// Marker for rows where the value wraps.
+    pub(crate) wrap_marker: Option<char>,
+
     /// Options for transforming the value.
     pub(crate) transformer: Option<Transformer>,
 
+    /// Options for the tab key.
+    pub(crate) tab: Option<Tab>,
+
+    /// Fixed value for a prompt that does not allow edits.
+    pub(crate) read_only: Option<String>,
+
+    /// Behavior for backspace when the buffer is empty.
+    pub(crate) empty_backspace: EmptyBackspace,
+
+    /// Behavior for the escape key.
+    pub(crate) escape: EscapeAction,
+
+    /// Behavior for submitting an empty buffer.
+    pub(crate) empty_accept: EmptyAccept,
+
+    /// Handling of whitespace in pasted text.
+    pub(crate) paste_whitespace: PasteWhitespace,
+
+    /// Hook transforming pasted text before it is inserted.
+    pub(crate) on_paste: Option<Mutex<PasteHook>>,
+
+    /// Continue on a new line when submitting with unclosed
+    /// brackets or quotes.
+    pub(crate) continue_unbalanced: bool,
+
+    /// Erasing a word stops at the whitespace before the word.
+    pub(crate) stop_at_whitespace: bool,
+
+    /// Abbreviations expanded when a word is completed.
+    pub(crate) abbreviations: HashMap<String, String>,
+
+    /// Prefix for commenting a line.
+    pub(crate) comment: Option<String>,
+
+    /// Command for the external editor.
+    pub(crate) editor: Option<String>,
+
+    /// Source of the events, defaults to the terminal.
+    pub(crate) event_source: Option<Mutex<Box<dyn EventSource>>>,
+
+    /// Record the key events read by the prompt.
+    pub(crate) record_events: bool,
+
+    /// Fixed terminal size.
+    pub(crate) size: Option<(u16, u16)>,
+
+    /// Only flush the output when the prompt is finished.
+    pub(crate) defer_flush: bool,
+
+    /// Submit once the value has this many graphemes.
+    pub(crate) auto_submit: Option<usize>,
+
+    /// Never ring the bell.
+    pub(crate) quiet: bool,
+
+    /// Receives the value whenever it changes.
+    pub(crate) change_sender: Option<Mutex<Sender<String>>>,
+
+    /// Hook called for each key event before the key bindings.
+    pub(crate) on_key: Option<Mutex<KeyHook>>,
+
+    /// Predicate determining whether an action is performed.
+    pub(crate) is_action_enabled: Option<Mutex<ActionPredicate>>,
+
+    /// Do not ring the bell for disabled actions.
+    pub(crate) silent_disabled: bool,
+
+    /// Completion implementation.
+    #[cfg(any(feature = "completion", doc))]
+    #[doc(cfg(feature = "completion"))]
+    pub(crate) completer: Option<Box<dyn Completer>>,
+
+    /// Completion implementation that runs in the background.
+    #[cfg(any(feature = "async", doc))]
+    #[doc(cfg(feature = "async"))]
+    pub(crate) async_completer: Option<Box<dyn AsyncCompleter>>,
+
+    /// Format for inserting the current time.
+    #[cfg(any(feature = "timestamp", doc))]
+    #[doc(cfg(feature = "timestamp"))]
+    pub(crate) timestamp_format: Option<String>,
+
+    /// Source of the current time for timestamps.
+    #[cfg(any(feature = "timestamp", doc))]
+    #[doc(cfg(feature = "timestamp"))]
+    pub(crate) clock: Option<fn() -> SystemTime>,
+
+    /// Maximum number of columns for listing completions.
+    #[cfg(any(feature = "completion", doc))]
+    #[doc(cfg(feature = "completion"))]
+    pub(crate) completion_columns: usize,
+
+    /// Hook called with each completion inserted into the buffer.
+    #[cfg(any(feature = "completion", doc))]
+    #[doc(cfg(feature = "completion"))]
+    pub(crate) on_completion: Option<Mutex<CompletionHook>>,
+
     /// History implementation.
     #[cfg(any(feature = "history", doc))]
     #[doc(cfg(feature = "history"))]
@@ -73,12 +217,390 @@ impl PromptOptions {
         self
     }
 
+    /// Configure validation that may also report a value as
+    /// [Validity::Incomplete] so that submitting the line
+    /// continues input on the next line.
+    ///
+    /// Runs in addition to [PromptOptions::validation], a value
+    /// is only valid when both accept it.
+    pub fn validity<F>(mut self, validity: F) -> Self
+    where
+        F: Fn(&str) -> Validity + 'static,
+    {
+        self.validity = Some(Box::new(validity));
+        self
+    }
+
+    /// Validate a value without the allowed values.
+    pub(crate) fn check(&self, value: &str) -> Validity {
+        if let Some(validation) = &self.validation {
+            if !(validation.validate)(value) {
+                return Validity::Invalid;
+            }
+        }
+        self.validity
+            .as_ref()
+            .map(|validity| (validity)(value))
+            .unwrap_or(Validity::Valid)
+    }
+
+    /// Configure the set of values that may be submitted.
+    ///
+    /// Other values are rejected with the message and the prompt
+    /// is displayed again; when the `completion` feature is enabled
+    /// the values are also used to complete the value unless a
+    /// completer is configured, which takes precedence.
+    pub fn allowed(mut self, allowed: Allowed) -> Self {
+        self.allowed = Some(allowed);
+        self
+    }
+
+    /// Configure the styles for the parts of the prompt.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Configure drawing a frame around the prefix and value.
+    pub fn border(mut self, border: Border) -> Self {
+        self.border = Some(border);
+        self
+    }
+
+    /// Configure a counter for the length of the value displayed
+    /// below the prompt and updated as the value changes.
+    pub fn counter(mut self, counter: Counter) -> Self {
+        self.counter = Some(counter);
+        self
+    }
+
+    /// Configure a marker drawn in the last column of rows where
+    /// a line wraps to the next row, for example `↩`.
+    ///
+    /// The marker is not drawn for explicit newlines.
+    pub fn wrap_marker(mut self, marker: char) -> Self {
+        self.wrap_marker = Some(marker);
+        self
+    }
+
+    /// Configure coloring the prefix and value when a line is
+    /// submitted depending upon the result of the validation.
+    pub fn accept_colors(mut self, accept_colors: AcceptColors) -> Self {
+        self.accept_colors = Some(accept_colors);
+        self
+    }
+
     /// Configure with a transformer.
     pub fn transformer(mut self, transformer: Transformer) -> Self {
         self.transformer = Some(transformer);
         self
     }
 
+    /// Add an abbreviation that is expanded when space or enter
+    /// is pressed directly after the abbreviation.
+    ///
+    /// The abbreviation must be a whole word, when the expansion
+    /// ends with whitespace no space is inserted after the expansion.
+    pub fn abbreviation<A: Into<String>, E: Into<String>>(
+        mut self,
+        abbreviation: A,
+        expansion: E,
+    ) -> Self {
+        self.abbreviations
+            .insert(abbreviation.into(), expansion.into());
+        self
+    }
+
+    /// Configure the prefix added to a line by
+    /// [KeyAction::ToggleComment](crate::KeyAction::ToggleComment).
+    ///
+    /// Default is a hash followed by a space (`# `).
+    pub fn comment<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.comment = Some(prefix.into());
+        self
+    }
+
+    /// Configure the command used to edit the value in an
+    /// external editor.
+    ///
+    /// The command is run by the shell with a file path appended;
+    /// when not set the `VISUAL` or `EDITOR` environment variable
+    /// is used.
+    pub fn editor<S: Into<String>>(mut self, command: S) -> Self {
+        self.editor = Some(command.into());
+        self
+    }
+
+    /// Configure a hook called for every key event before it
+    /// is dispatched to the key bindings.
+    ///
+    /// The hook also runs first for keys handled by a pending
+    /// action, the reverse search, the history picker and the
+    /// completion listing so that it sees every key.
+    pub fn on_key<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(&KeyEvent) -> Intercept + 'static,
+    {
+        self.on_key = Some(Mutex::new(Box::new(hook)));
+        self
+    }
+
+    /// Configure a predicate called with each action and the
+    /// current value before the action is performed.
+    ///
+    /// Actions for which the predicate returns `false` do nothing
+    /// and ring the bell unless the prompt is quiet or
+    /// [PromptOptions::silent_disabled] is set; a numeric argument
+    /// for a disabled action is discarded.
+    ///
+    /// Keys handled by the reverse search and the history picker
+    /// and Tab while completions are listed are not actions so the
+    /// predicate is not called for them.
+    pub fn is_action_enabled<F>(mut self, predicate: F) -> Self
+    where
+        F: FnMut(&KeyAction, &str) -> bool + 'static,
+    {
+        self.is_action_enabled = Some(Mutex::new(Box::new(predicate)));
+        self
+    }
+
+    /// Configure whether actions disabled by
+    /// [PromptOptions::is_action_enabled] do nothing without
+    /// ringing the bell.
+    pub fn silent_disabled(mut self, silent: bool) -> Self {
+        self.silent_disabled = silent;
+        self
+    }
+
+    /// Configure the source of events.
+    ///
+    /// When set the prompt does not use raw mode or query the
+    /// terminal size and cursor position.
+    pub fn event_source(mut self, source: Box<dyn EventSource>) -> Self {
+        self.event_source = Some(Mutex::new(source));
+        self
+    }
+
+    /// Configure a fixed terminal size as columns and rows.
+    ///
+    /// The size is used instead of querying the terminal and
+    /// resize events are ignored, this is intended for tests
+    /// where the output must not depend on the terminal.
+    pub fn size(mut self, size: (u16, u16)) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Configure recording the key events read by the prompt
+    /// so they are returned in the [Response](crate::Response).
+    pub fn record_events(mut self, record: bool) -> Self {
+        self.record_events = record;
+        self
+    }
+
+    /// Configure the prompt to display a fixed value that
+    /// cannot be edited.
+    ///
+    /// Actions that change the value are ignored so only moving
+    /// the cursor, submitting and aborting are available;
+    /// submitting returns the fixed value even when it is empty
+    /// and [PromptOptions::empty_accept] is configured.
+    pub fn read_only<S: Into<String>>(mut self, value: S) -> Self {
+        self.read_only = Some(value.into());
+        self
+    }
+
+    /// Configure submitting the value without a key once a key
+    /// or a paste makes the value at least `length` graphemes long.
+    ///
+    /// Intended for fixed length input such as a code, combine
+    /// with [PromptOptions::on_key] to only accept some characters.
+    pub fn auto_submit(mut self, length: usize) -> Self {
+        self.auto_submit = Some(length);
+        self
+    }
+
+    /// Configure disabling the bell entirely.
+    ///
+    /// This overrides behaviors that are configured to ring
+    /// the bell such as [EmptyBackspace::Bell].
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Configure a channel that receives the value each time
+    /// it is changed so another thread may mirror the input.
+    ///
+    /// Values are not sent after the receiver is dropped.
+    pub fn change_sender(mut self, sender: Sender<String>) -> Self {
+        self.change_sender = Some(Mutex::new(sender));
+        self
+    }
+
+    /// Configure deferring flushing the output until the prompt
+    /// is finished rather than after every key.
+    ///
+    /// This is intended for benchmarking the editing logic
+    /// without the cost of terminal output.
+    pub fn defer_flush(mut self, defer_flush: bool) -> Self {
+        self.defer_flush = defer_flush;
+        self
+    }
+
+    /// Configure the behavior for backspace when the buffer is empty.
+    pub fn empty_backspace(mut self, empty_backspace: EmptyBackspace) -> Self {
+        self.empty_backspace = empty_backspace;
+        self
+    }
+
+    /// Configure the behavior for the escape key.
+    pub fn escape(mut self, escape: EscapeAction) -> Self {
+        self.escape = escape;
+        self
+    }
+
+    /// Configure the handling of whitespace in pasted text.
+    ///
+    /// Only applies to text given to [Prompt::paste](crate::Prompt::paste),
+    /// the blocking functions such as [prompt](crate::prompt) handle
+    /// pasted text as typed keys and ignore this option; see
+    /// [Required::trim] for trimming the value when it is submitted.
+    pub fn paste_whitespace(
+        mut self,
+        paste_whitespace: PasteWhitespace,
+    ) -> Self {
+        self.paste_whitespace = paste_whitespace;
+        self
+    }
+
+    /// Configure a hook that receives pasted text and returns
+    /// the text to insert.
+    ///
+    /// The hook runs before the handling of whitespace and
+    /// newlines in the pasted text.
+    ///
+    /// Only applies to text given to [Prompt::paste](crate::Prompt::paste),
+    /// the blocking functions such as [prompt](crate::prompt) handle
+    /// pasted text as typed keys and never call the hook.
+    pub fn on_paste<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(String) -> String + 'static,
+    {
+        self.on_paste = Some(Mutex::new(Box::new(hook)));
+        self
+    }
+
+    /// Configure the behavior for submitting when the buffer is empty.
+    pub fn empty_accept(mut self, empty_accept: EmptyAccept) -> Self {
+        self.empty_accept = empty_accept;
+        self
+    }
+
+    /// Configure submitting a line that contains an unclosed
+    /// bracket or quote to insert a newline instead so that
+    /// input continues on the next line.
+    pub fn continue_unbalanced(mut self, continue_unbalanced: bool) -> Self {
+        self.continue_unbalanced = continue_unbalanced;
+        self
+    }
+
+    /// Configure erasing the word before the cursor to stop
+    /// at whitespace.
+    ///
+    /// By default a run of whitespace before the cursor is erased
+    /// together with the preceding word, when this is set the
+    /// whitespace is erased on its own like the `unix-word-rubout`
+    /// of some shells.
+    pub fn stop_at_whitespace(mut self, stop_at_whitespace: bool) -> Self {
+        self.stop_at_whitespace = stop_at_whitespace;
+        self
+    }
+
+    /// Configure the tab key.
+    pub fn tab(mut self, tab: Tab) -> Self {
+        self.tab = Some(tab);
+        self
+    }
+
+    #[cfg(any(feature = "completion", doc))]
+    #[doc(cfg(feature = "completion"))]
+    /// Configure with a completer.
+    pub fn completer(mut self, completer: Box<dyn Completer>) -> Self {
+        self.completer = Some(completer);
+        self
+    }
+
+    #[cfg(any(feature = "async", doc))]
+    #[doc(cfg(feature = "async"))]
+    /// Configure with a completer that computes the candidates
+    /// in the background.
+    ///
+    /// This is used for the tab key when no other completer
+    /// is configured.
+    pub fn async_completer(
+        mut self,
+        completer: Box<dyn AsyncCompleter>,
+    ) -> Self {
+        self.async_completer = Some(completer);
+        self
+    }
+
+    #[cfg(any(feature = "completion", doc))]
+    #[doc(cfg(feature = "completion"))]
+    /// Configure the maximum number of columns used to list
+    /// completion candidates.
+    ///
+    /// When the word cannot be extended the candidates are listed
+    /// below the prompt in as many columns as fit the terminal
+    /// width; pressing tab again inserts each candidate in turn.
+    /// Zero, the default, does not limit the number of columns.
+    pub fn completion_columns(mut self, columns: usize) -> Self {
+        self.completion_columns = columns;
+        self
+    }
+
+    #[cfg(any(feature = "completion", doc))]
+    #[doc(cfg(feature = "completion"))]
+    /// Configure a hook called with the text of each accepted
+    /// completion.
+    ///
+    /// The hook is called when a candidate or the prefix shared by
+    /// the candidates is inserted and when the listing is closed
+    /// with a candidate selected; it is not called when the
+    /// candidates are listed and no candidate is selected.
+    pub fn on_completion<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(&str) + 'static,
+    {
+        self.on_completion = Some(Mutex::new(Box::new(hook)));
+        self
+    }
+
+    #[cfg(any(feature = "timestamp", doc))]
+    #[doc(cfg(feature = "timestamp"))]
+    /// Configure the format for the time inserted by
+    /// [KeyAction::InsertTimestamp](crate::KeyAction::InsertTimestamp).
+    ///
+    /// Supports the `strftime` specifiers `%Y`, `%m`, `%d`, `%H`, `%M`
+    /// and `%S` for the time in UTC, `%s` for the seconds since the
+    /// epoch and `%%` for a percent sign. An invalid format falls
+    /// back to the default format `%Y-%m-%d %H:%M:%S`.
+    pub fn timestamp_format<S: Into<String>>(mut self, format: S) -> Self {
+        self.timestamp_format = Some(format.into());
+        self
+    }
+
+    #[cfg(any(feature = "timestamp", doc))]
+    #[doc(cfg(feature = "timestamp"))]
+    /// Configure the function that gets the current time for
+    /// [KeyAction::InsertTimestamp](crate::KeyAction::InsertTimestamp),
+    /// by default the system clock is used.
+    pub fn clock(mut self, clock: fn() -> SystemTime) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
     #[cfg(any(feature = "history", doc))]
     #[doc(cfg(feature = "history"))]
     /// Configure with a history.
@@ -107,13 +629,44 @@ pub struct Required {
 pub struct PassWord {
     /// Character to echo for each character input.
     ///
+    /// A single character is echoed for every grapheme so wide
+    /// characters occupy one column when masked.
+    ///
     /// Default is to print the asterisk ('*').
     pub echo: Option<char>,
+
+    /// Write nothing for the value, not even the mask
+    /// characters, so the length of the value is not revealed.
+    ///
+    /// The cursor stays after the prefix and `echo` is ignored.
+    pub hidden: bool,
+
+    /// Leave a value shown with
+    /// [KeyAction::ToggleMask](crate::KeyAction::ToggleMask)
+    /// displayed when it is submitted.
+    ///
+    /// By default the value is masked again before the cursor
+    /// moves below the prompt.
+    pub keep_shown: bool,
+}
+
+impl PassWord {
+    /// Password options that hide the value entirely.
+    pub fn hidden() -> Self {
+        Self {
+            hidden: true,
+            ..Default::default()
+        }
+    }
 }
 
 impl Default for PassWord {
     fn default() -> Self {
-        Self { echo: Some('*') }
+        Self {
+            echo: Some('*'),
+            hidden: false,
+            keep_shown: false,
+        }
     }
 }
 
@@ -122,6 +675,226 @@ impl Default for PassWord {
 pub struct MultiLine {
     /// Show the prompt for each line of input.
     pub repeat_prompt: bool,
+
+    /// Line ending used in the returned value.
+    pub line_ending: LineEnding,
+
+    /// Remove one trailing line ending from the returned value.
+    pub trim_trailing_newline: bool,
+}
+
+impl MultiLine {
+    /// Convert the line endings of a value.
+    pub(crate) fn normalize(&self, value: String) -> String {
+        let mut value = match self.line_ending {
+            LineEnding::Lf => value,
+            LineEnding::CrLf => value.replace('\n', "\r\n"),
+        };
+        if self.trim_trailing_newline {
+            let ending = self.line_ending.as_str();
+            if value.ends_with(ending) {
+                value.truncate(value.len() - ending.len());
+            }
+        }
+        value
+    }
+}
+
+/// Line ending for multiline values.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum LineEnding {
+    /// Newline (`\n`).
+    #[default]
+    Lf,
+    /// Carriage return and newline (`\r\n`).
+    CrLf,
+}
+
+impl LineEnding {
+    /// Get the characters for the line ending.
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Behavior for the escape key.
+///
+/// Escape is handled after a reverse search or picker which
+/// use escape to cancel, the [PromptOptions::on_key] hook and a
+/// key binding for escape take precedence over the action.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum EscapeAction {
+    /// Do nothing.
+    #[default]
+    Ignore,
+    /// Abort the prompt.
+    Abort,
+    /// Clear the buffer.
+    Clear,
+    /// Treat escape as a meta prefix so the next key is
+    /// handled as if it was pressed with alt, for example
+    /// escape followed by `w` is the same as `Alt+w`.
+    Meta,
+}
+
+/// Behavior for backspace when the buffer is empty.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum EmptyBackspace {
+    /// Do nothing.
+    #[default]
+    Ignore,
+    /// Ring the terminal bell.
+    Bell,
+    /// Leave the prompt so the caller can go back to
+    /// a previous prompt.
+    ///
+    /// The response for the prompt has the `back` flag set
+    /// and validation is skipped.
+    Back,
+}
+
+/// Behavior for submitting when the buffer is empty.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub enum EmptyAccept {
+    /// Submit the empty value.
+    #[default]
+    Accept,
+    /// Ring the terminal bell and keep editing.
+    Reject,
+    /// Submit a default value instead.
+    Default(String),
+}
+
+/// Handling of whitespace in pasted text.
+///
+/// Newlines in pasted text are replaced with spaces
+/// unless multiline input is enabled.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum PasteWhitespace {
+    /// Insert pasted text unchanged.
+    #[default]
+    Keep,
+    /// Remove leading and trailing whitespace.
+    Trim,
+    /// Remove leading and trailing whitespace and replace runs
+    /// of whitespace on each line with a single space.
+    Collapse,
+}
+
+/// Whitespace inserted for the tab key.
+#[derive(Debug, Default, Clone, Copy)]
+pub enum Indent {
+    /// Insert a literal tab character.
+    #[default]
+    Tab,
+    /// Insert a number of spaces.
+    Spaces(u8),
+}
+
+impl Indent {
+    /// Get the whitespace to insert.
+    pub(crate) fn whitespace(&self) -> String {
+        match self {
+            Self::Tab => '\t'.to_string(),
+            Self::Spaces(amount) => " ".repeat(*amount as usize),
+        }
+    }
+}
+
+/// The options for the tab key.
+///
+/// When a completer is configured and `complete` is set the
+/// tab key completes the word before the cursor and only
+/// inserts whitespace when there is nothing to complete;
+/// otherwise whitespace is always inserted.
+///
+/// Without these options the tab key only performs completion.
+pub struct Tab {
+    /// Whitespace to insert.
+    pub indent: Indent,
+
+    /// Whether completion takes precedence over inserting whitespace.
+    pub complete: bool,
+}
+
+impl Default for Tab {
+    fn default() -> Self {
+        Self {
+            indent: Default::default(),
+            complete: true,
+        }
+    }
+}
+
+/// Result of validating a value.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Validity {
+    /// The value is accepted.
+    Valid,
+    /// The value is rejected and the prompt is displayed again.
+    Invalid,
+    /// The value is not finished yet, submitting the line
+    /// inserts a newline so input continues on the next line.
+    ///
+    /// When the value is submitted by another action
+    /// it is treated as invalid.
+    Incomplete,
+}
+
+impl From<bool> for Validity {
+    fn from(valid: bool) -> Self {
+        if valid {
+            Self::Valid
+        } else {
+            Self::Invalid
+        }
+    }
+}
+
+/// Set of values that may be submitted.
+pub struct Allowed {
+    /// Values that are accepted.
+    pub values: Vec<String>,
+
+    /// Compare the value with the allowed values respecting case.
+    ///
+    /// When case is ignored the accepted value is the allowed
+    /// value as it is written in this set.
+    pub case_sensitive: bool,
+
+    /// Message written when a value is rejected.
+    pub message: String,
+}
+
+impl Allowed {
+    /// Create a case sensitive set of allowed values.
+    pub fn new<S: AsRef<str>>(values: &[S]) -> Self {
+        let values: Vec<String> =
+            values.iter().map(|v| v.as_ref().to_string()).collect();
+        let message = format!("value must be one of: {}", values.join(", "));
+        Self {
+            values,
+            case_sensitive: true,
+            message,
+        }
+    }
+
+    /// Find the allowed value matching a value.
+    pub(crate) fn find(&self, value: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|v| {
+                if self.case_sensitive {
+                    *v == value
+                } else {
+                    v.to_lowercase() == value.to_lowercase()
+                }
+            })
+            .map(|v| &v[..])
+    }
 }
 
 /// The options for validation.
@@ -141,10 +914,116 @@ impl Default for Validation {
     }
 }
 
+/// Styles for the parts of a prompt.
+///
+/// The default theme does not style any part, the colors
+/// for a submitted line replace the prefix and value styles.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Theme {
+    /// Style for the prefix including the status shown
+    /// instead of the prefix while searching.
+    pub prefix: ContentStyle,
+    /// Style for the value.
+    pub value: ContentStyle,
+    /// Style for the lines below the value such as
+    /// completion candidates.
+    pub info: ContentStyle,
+}
+
+/// Characters and style for a frame around the prompt.
+///
+/// The frame uses the terminal width and the value wraps
+/// inside the frame, lines below the prompt such as
+/// completion candidates are drawn below the frame.
+#[derive(Debug, Clone, Copy)]
+pub struct Border {
+    /// Character for the top left corner.
+    pub top_left: char,
+    /// Character for the top right corner.
+    pub top_right: char,
+    /// Character for the bottom left corner.
+    pub bottom_left: char,
+    /// Character for the bottom right corner.
+    pub bottom_right: char,
+    /// Character for the top and bottom edges.
+    pub horizontal: char,
+    /// Character for the left and right edges.
+    pub vertical: char,
+    /// Style for the frame.
+    pub style: ContentStyle,
+}
+
+impl Default for Border {
+    fn default() -> Self {
+        Self {
+            top_left: '┌',
+            top_right: '┐',
+            bottom_left: '└',
+            bottom_right: '┘',
+            horizontal: '─',
+            vertical: '│',
+            style: Default::default(),
+        }
+    }
+}
+
+/// Counter for the length of the value.
+///
+/// The counter is displayed as `N/Max` when there is a maximum
+/// and `N` otherwise; it is not displayed for hidden passwords.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Counter {
+    /// Maximum length displayed after the count.
+    ///
+    /// Defaults to the [PromptOptions::auto_submit] length when
+    /// counting graphemes, the maximum is not enforced otherwise.
+    pub maximum: Option<usize>,
+    /// Count the columns used to display the value
+    /// instead of the graphemes.
+    pub columns: bool,
+}
+
+impl Counter {
+    /// Text of the counter for a value.
+    pub(crate) fn text(&self, value: &str) -> String {
+        let count = if self.columns {
+            UnicodeWidthStr::width(value)
+        } else {
+            UnicodeSegmentation::graphemes(value, true).count()
+        };
+        match self.maximum {
+            Some(maximum) => format!("{}/{}", count, maximum),
+            None => count.to_string(),
+        }
+    }
+}
+
+/// Colors for the prefix and value of a submitted line.
+///
+/// When there is no validation every value is valid.
+#[derive(Debug, Clone, Copy)]
+pub struct AcceptColors {
+    /// Color for a valid value.
+    pub valid: Color,
+    /// Color for an invalid or incomplete value.
+    pub invalid: Color,
+}
+
+impl Default for AcceptColors {
+    fn default() -> Self {
+        Self {
+            valid: Color::Green,
+            invalid: Color::Red,
+        }
+    }
+}
+
+type TransformHandler = Box<dyn Fn(&str) -> Cow<'_, str>>;
+
 /// The options for transforming the value.
 pub struct Transformer {
     /// Closure to transform the value.
-    pub transform: Box<dyn Fn(&str) -> Cow<'_, str>>,
+    pub transform: TransformHandler,
 }
 
 impl Default for Transformer {
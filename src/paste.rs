@@ -0,0 +1,51 @@
+//! Support for pasted text.
+//!
+//! Pasted text is given to [Prompt::paste](crate::Prompt::paste)
+//! by a caller that can distinguish a paste from typed keys.
+use crate::{terminal_buffer::normalize_newlines, PasteWhitespace};
+
+/// Prepare pasted text for insertion.
+///
+/// Newlines are replaced with spaces unless the prompt accepts
+/// multiline input and then whitespace is handled according to
+/// the paste whitespace option.
+pub(crate) fn sanitize(
+    text: &str,
+    whitespace: PasteWhitespace,
+    multiline: bool,
+) -> String {
+    let text = normalize_newlines(text);
+    let text = if multiline {
+        text
+    } else {
+        text.replace('\n', " ")
+    };
+    match whitespace {
+        PasteWhitespace::Keep => text,
+        PasteWhitespace::Trim => text.trim().to_string(),
+        PasteWhitespace::Collapse => text
+            .trim()
+            .split('\n')
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_whitespace() {
+        let text = "  a  b  ";
+        assert_eq!(text, sanitize(text, PasteWhitespace::Keep, false));
+        assert_eq!("a  b", sanitize(text, PasteWhitespace::Trim, false));
+        assert_eq!("a b", sanitize(text, PasteWhitespace::Collapse, false));
+        assert_eq!(
+            "a b\nc",
+            sanitize(" a \t b\n  c\n", PasteWhitespace::Collapse, true)
+        );
+        assert_eq!("a  b ", sanitize("a\n b\n", PasteWhitespace::Keep, false));
+    }
+}
@@ -0,0 +1,144 @@
+//! Fuzzy picker for selecting a history item.
+
+/// Maximum number of matches to display.
+const ROWS: usize = 10;
+
+/// State for a fuzzy history picker.
+pub(crate) struct Picker {
+    /// Text to match against.
+    pub query: String,
+    /// Buffer before the picker was opened.
+    pub saved: String,
+    /// Indices of the matching history items, best match first.
+    matches: Vec<usize>,
+    /// Position of the selected match.
+    selected: usize,
+}
+
+impl Picker {
+    /// Open a new picker.
+    pub fn new(saved: String) -> Self {
+        Self {
+            query: String::new(),
+            saved,
+            matches: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    /// Find the items matching the query and select the best match.
+    ///
+    /// Matches with the same score are ordered by most recent.
+    pub fn filter(&mut self, items: &[String]) {
+        let mut scored: Vec<(usize, usize)> = items
+            .iter()
+            .enumerate()
+            .rev()
+            .filter_map(|(index, item)| {
+                fuzzy_score(item, &self.query).map(|score| (score, index))
+            })
+            .collect();
+        scored.sort_by_key(|(score, _)| *score);
+        self.matches = scored.into_iter().map(|(_, index)| index).collect();
+        self.selected = 0;
+    }
+
+    /// Index of the selected history item.
+    pub fn selected(&self) -> Option<usize> {
+        self.matches.get(self.selected).copied()
+    }
+
+    /// Select the match above the current selection.
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Select the match below the current selection.
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Prefix to display while picking.
+    pub fn prefix(&self) -> String {
+        format!("({}/{}) ", self.matches.len().min(ROWS), self.matches.len())
+    }
+
+    /// Lines listing the matches with the selection marked.
+    pub fn lines(&self, items: &[String]) -> Vec<String> {
+        let start = self.selected.saturating_sub(ROWS - 1);
+        self.matches
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(ROWS)
+            .map(|(position, index)| {
+                let marker = if position == self.selected { '>' } else { ' ' };
+                format!("{} {}", marker, items[*index].replace('\n', " "))
+            })
+            .collect()
+    }
+}
+
+/// Score an item when the characters of the query appear in
+/// order in the item ignoring case.
+///
+/// The score is the number of characters between the first and
+/// last matched characters that are not in the query so lower
+/// scores are better matches.
+pub(crate) fn fuzzy_score(item: &str, query: &str) -> Option<usize> {
+    let mut query = query.chars().flat_map(char::to_lowercase).peekable();
+    let mut first = None;
+    let mut last = 0;
+    let mut matched = 0;
+    for (position, c) in item.chars().flat_map(char::to_lowercase).enumerate() {
+        match query.peek() {
+            Some(q) if *q == c => {
+                query.next();
+                first.get_or_insert(position);
+                last = position;
+                matched += 1;
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+    if query.peek().is_some() {
+        None
+    } else if let Some(first) = first {
+        Some(last - first + 1 - matched)
+    } else {
+        Some(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_match() {
+        assert_eq!(Some(0), fuzzy_score("git status", ""));
+        assert_eq!(Some(0), fuzzy_score("git status", "STA"));
+        assert_eq!(Some(3), fuzzy_score("git status", "gs"));
+        assert_eq!(None, fuzzy_score("git status", "sg"));
+
+        let items = vec![
+            "git status".to_string(),
+            "ls -la".to_string(),
+            "git stash".to_string(),
+            "cargo test".to_string(),
+        ];
+        let mut picker = Picker::new(String::new());
+        picker.query = "gsta".to_string();
+        picker.filter(&items);
+        assert_eq!(Some(2), picker.selected());
+        assert_eq!(vec!["> git stash", "  git status"], picker.lines(&items));
+        picker.select_next();
+        picker.select_next();
+        assert_eq!(Some(0), picker.selected());
+        picker.select_previous();
+        assert_eq!(Some(2), picker.selected());
+    }
+}
@@ -0,0 +1,92 @@
+//! Prefix for a prompt prepared for rendering.
+use std::borrow::Cow;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Prefix for a prompt with the graphemes and their widths
+/// computed once so the prefix may be reused across prompts.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Prefix {
+    text: String,
+    /// Byte offset and columns for each grapheme.
+    graphemes: Vec<(usize, usize)>,
+}
+
+impl Prefix {
+    /// Prepare a prefix.
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        let text = text.into();
+        let graphemes = UnicodeSegmentation::grapheme_indices(&text[..], true)
+            .map(|(offset, g)| (offset, UnicodeWidthStr::width(g)))
+            .collect();
+        Self { text, graphemes }
+    }
+
+    /// Get the text of the prefix.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Get the number of columns used to display the prefix.
+    pub fn columns(&self) -> usize {
+        self.graphemes.iter().map(|(_, cols)| cols).sum()
+    }
+
+    /// Iterate the graphemes and their widths.
+    pub(crate) fn graphemes(&self) -> impl Iterator<Item = (&str, usize)> {
+        let ends = self
+            .graphemes
+            .iter()
+            .skip(1)
+            .map(|(offset, _)| *offset)
+            .chain(std::iter::once(self.text.len()));
+        self.graphemes
+            .iter()
+            .zip(ends)
+            .map(|((start, cols), end)| (&self.text[*start..end], *cols))
+    }
+}
+
+/// Types that may be used as the prefix for a prompt.
+///
+/// Strings are prepared for every prompt, a [Prefix] is
+/// prepared once when it is created.
+pub trait AsPrefix {
+    /// Get the prepared prefix.
+    fn as_prefix(&self) -> Cow<'_, Prefix>;
+}
+
+impl<T: AsRef<str> + ?Sized> AsPrefix for T {
+    fn as_prefix(&self) -> Cow<'_, Prefix> {
+        Cow::Owned(Prefix::new(self.as_ref()))
+    }
+}
+
+impl AsPrefix for Prefix {
+    fn as_prefix(&self) -> Cow<'_, Prefix> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl AsPrefix for &Prefix {
+    fn as_prefix(&self) -> Cow<'_, Prefix> {
+        Cow::Borrowed(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_graphemes() {
+        let prefix = Prefix::new("日e\u{301}> ");
+        let graphemes: Vec<_> = prefix.graphemes().collect();
+        assert_eq!(
+            vec![("日", 2), ("e\u{301}", 1), (">", 1), (" ", 1)],
+            graphemes
+        );
+        assert_eq!(5, prefix.columns());
+        assert_eq!(prefix, *"日e\u{301}> ".as_prefix());
+    }
+}
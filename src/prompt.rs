@@ -0,0 +1,342 @@
+//! Prompt driven by an event loop owned by the caller.
+use anyhow::Result;
+use crossterm::event::Event;
+use std::io::Write;
+
+use crate::editor::{Editor, Flow};
+use crate::{AsPrefix, PromptOptions, Response};
+
+/// Outcome of a prompt, each variant has the response
+/// for the value when the prompt finished.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PromptOutcome {
+    /// The value was submitted.
+    Submitted(Response),
+    /// The prompt was aborted.
+    Aborted(Response),
+    /// The prompt was left to go back to a previous prompt.
+    ///
+    /// See [EmptyBackspace::Back](crate::EmptyBackspace::Back).
+    Back(Response),
+}
+
+impl PromptOutcome {
+    /// Get the response regardless of the outcome.
+    pub fn into_response(self) -> Response {
+        match self {
+            Self::Submitted(response)
+            | Self::Aborted(response)
+            | Self::Back(response) => response,
+        }
+    }
+}
+
+/// Prompt that renders and handles events without reading
+/// from the terminal so it may be used in an existing event loop.
+///
+/// The caller is responsible for raw mode, the terminal size and
+/// reading events; [prompt](crate::prompt) reads events from the
+/// terminal or the configured event source and passes them here.
+///
+/// Events are not recorded and an outcome is returned once, the
+/// prompt is finished after an outcome and should be dropped.
+pub struct Prompt<'a> {
+    editor: Editor<'a>,
+    options: &'a PromptOptions,
+    /// Value last sent to the change sender.
+    last_value: String,
+}
+
+impl<'a> Prompt<'a> {
+    /// Create a prompt.
+    pub fn new<P: AsPrefix + ?Sized>(
+        prefix: &'a P,
+        options: &'a PromptOptions,
+    ) -> Self {
+        let mut editor = Editor::new(prefix, options);
+        if let Some(size) = options.size {
+            editor.set_size(size);
+        }
+        let last_value = editor.value().to_string();
+        Self {
+            editor,
+            options,
+            last_value,
+        }
+    }
+
+    /// Set the terminal size.
+    ///
+    /// Has no effect when a size is configured in the options.
+    pub fn set_size(&mut self, size: (u16, u16)) {
+        if self.options.size.is_none() {
+            self.editor.set_size(size);
+        }
+    }
+
+    /// Write the prompt with the cursor at the given position.
+    pub fn render<W>(
+        &mut self,
+        writer: &mut W,
+        position: (u16, u16),
+    ) -> Result<()>
+    where
+        W: Write,
+    {
+        self.editor.start(writer, position)
+    }
+
+    /// Handle an event, returning the outcome when the
+    /// prompt is finished.
+    pub fn handle_event<W>(
+        &mut self,
+        writer: &mut W,
+        event: &Event,
+    ) -> Result<Option<PromptOutcome>>
+    where
+        W: Write,
+    {
+        let flow = match event {
+            Event::Key(event) => self.editor.handle_key(writer, event)?,
+            Event::Mouse(_event) => Flow::Continue,
+            Event::Resize(width, height) => {
+                if self.options.size.is_none() {
+                    self.editor.set_size((*width, *height));
+                    self.editor.redraw(writer)?;
+                }
+                Flow::Continue
+            }
+        };
+        self.send_change();
+        Ok(self.outcome(flow))
+    }
+
+    /// Insert pasted text, returning the outcome when the
+    /// paste submitted the prompt.
+    pub fn paste<W>(
+        &mut self,
+        writer: &mut W,
+        text: &str,
+    ) -> Result<Option<PromptOutcome>>
+    where
+        W: Write,
+    {
+        let flow = self.editor.paste(writer, text)?;
+        self.send_change();
+        Ok(self.outcome(flow))
+    }
+
+    /// Get the current value.
+    pub fn value(&self) -> &str {
+        self.editor.value()
+    }
+
+    /// Set the mask character and redraw the prompt, `None`
+    /// shows the value.
+    ///
+    /// The value is unchanged, only the displayed text is affected.
+    pub fn set_mask<W>(
+        &mut self,
+        writer: &mut W,
+        echo: Option<char>,
+    ) -> Result<()>
+    where
+        W: Write,
+    {
+        self.editor.set_mask(writer, echo)
+    }
+
+    /// Suspend the prompt so that another program may use the terminal.
+    ///
+    /// The cursor is moved below the prompt and raw mode is disabled
+    /// when it was enabled; call [Prompt::resume] before handling
+    /// more events.
+    pub fn suspend<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.editor.suspend(writer)
+    }
+
+    /// Resume a suspended prompt redrawing it below any
+    /// output written while suspended.
+    ///
+    /// Raw mode is enabled again when it was enabled before
+    /// the prompt was suspended.
+    pub fn resume<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.editor.resume(writer)
+    }
+
+    /// Determine if the prompt is waiting for completion candidates,
+    /// while waiting [Prompt::tick] should be called periodically.
+    pub fn is_waiting(&self) -> bool {
+        self.editor.is_waiting()
+    }
+
+    /// Check for completion candidates advancing the spinner.
+    pub fn tick<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.editor.tick(writer)?;
+        self.send_change();
+        Ok(())
+    }
+
+    /// Send the value to the change sender when it changed.
+    fn send_change(&mut self) {
+        if let Some(sender) = &self.options.change_sender {
+            let value = self.editor.value();
+            if value != self.last_value {
+                self.last_value = value.to_string();
+                // The receiver may have stopped listening
+                let _ = sender.lock().unwrap().send(self.last_value.clone());
+            }
+        }
+    }
+
+    /// Outcome for the flow after an event.
+    fn outcome(&self, flow: Flow) -> Option<PromptOutcome> {
+        let outcome = match flow {
+            Flow::Continue => return None,
+            Flow::Submit(submit) => {
+                PromptOutcome::Submitted(self.response(submit, false))
+            }
+            Flow::Abort => PromptOutcome::Aborted(self.response(None, false)),
+            Flow::Back => PromptOutcome::Back(self.response(None, true)),
+        };
+        Some(outcome)
+    }
+
+    /// Response for the current value.
+    fn response(&self, submit: Option<&'static str>, back: bool) -> Response {
+        let value = self.editor.value().to_string();
+        let value = match &self.options.multiline {
+            Some(multiline) => multiline.normalize(value),
+            None => value,
+        };
+        Response {
+            value,
+            submit,
+            back,
+            events: Vec::new(),
+            wrapped: self.editor.is_wrapped(),
+            visible: self.editor.visible_line(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EmptyBackspace;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    fn key(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn handle_events() -> Result<()> {
+        let options = PromptOptions::new();
+        let mut writer = Vec::new();
+        let mut prompt = Prompt::new("> ", &options);
+        prompt.set_size((80, 24));
+        prompt.render(&mut writer, (0, 0))?;
+        assert!(String::from_utf8_lossy(&writer).ends_with("> \x1b[1;3H"));
+
+        for c in "hi".chars() {
+            let outcome =
+                prompt.handle_event(&mut writer, &key(KeyCode::Char(c)))?;
+            assert_eq!(None, outcome);
+        }
+        assert_eq!(None, prompt.paste(&mut writer, " there")?);
+        let outcome =
+            prompt.handle_event(&mut writer, &Event::Resize(4, 24))?;
+        assert_eq!(None, outcome);
+
+        let outcome = prompt.handle_event(&mut writer, &key(KeyCode::Enter))?;
+        let response = match outcome {
+            Some(PromptOutcome::Submitted(response)) => response,
+            _ => panic!("prompt was not submitted"),
+        };
+        assert_eq!("hi there", response.value);
+        assert!(response.wrapped);
+        assert_eq!(None, response.submit);
+        Ok(())
+    }
+
+    #[test]
+    fn set_mask() -> Result<()> {
+        let options = PromptOptions::new();
+        let mut writer = Vec::new();
+        let mut prompt = Prompt::new("> ", &options);
+        prompt.set_size((80, 24));
+        prompt.render(&mut writer, (0, 0))?;
+        for c in "abc".chars() {
+            prompt.handle_event(&mut writer, &key(KeyCode::Char(c)))?;
+        }
+
+        let mut writer = Vec::new();
+        prompt.set_mask(&mut writer, Some('*'))?;
+        assert!(String::from_utf8_lossy(&writer).contains("***"));
+        assert_eq!("abc", prompt.value());
+
+        let mut writer = Vec::new();
+        prompt.set_mask(&mut writer, None)?;
+        assert!(String::from_utf8_lossy(&writer).contains("abc"));
+        assert_eq!("abc", prompt.value());
+        Ok(())
+    }
+
+    #[test]
+    fn suspend_resume() -> Result<()> {
+        let options = PromptOptions::new();
+        let mut writer = Vec::new();
+        let mut prompt = Prompt::new("> ", &options);
+        prompt.set_size((80, 24));
+        prompt.render(&mut writer, (0, 3))?;
+        prompt.handle_event(&mut writer, &key(KeyCode::Char('a')))?;
+
+        let mut writer = Vec::new();
+        prompt.suspend(&mut writer)?;
+        assert!(String::from_utf8_lossy(&writer).ends_with("\r\n"));
+
+        // Redrawn on the row after the suspended prompt
+        let mut writer = Vec::new();
+        prompt.resume(&mut writer)?;
+        let out = String::from_utf8_lossy(&writer).into_owned();
+        assert!(out.contains("\x1b[5;1H\x1b[J> a"));
+
+        prompt.handle_event(&mut writer, &key(KeyCode::Char('b')))?;
+        let outcome = prompt.handle_event(&mut writer, &key(KeyCode::Enter))?;
+        assert_eq!("ab", outcome.unwrap().into_response().value);
+        Ok(())
+    }
+
+    #[test]
+    fn handle_events_outcomes() -> Result<()> {
+        let options = PromptOptions::new();
+        let mut prompt = Prompt::new("> ", &options);
+        let ctrl_c = Event::Key(KeyEvent::new(
+            KeyCode::Char('c'),
+            KeyModifiers::CONTROL,
+        ));
+        prompt.handle_event(&mut Vec::new(), &key(KeyCode::Char('x')))?;
+        let outcome = prompt.handle_event(&mut Vec::new(), &ctrl_c)?;
+        assert!(matches!(outcome, Some(PromptOutcome::Aborted(_))));
+        assert_eq!("x", outcome.unwrap().into_response().value);
+
+        let options =
+            PromptOptions::new().empty_backspace(EmptyBackspace::Back);
+        let mut prompt = Prompt::new("> ", &options);
+        let outcome =
+            prompt.handle_event(&mut Vec::new(), &key(KeyCode::Backspace))?;
+        let response = outcome.unwrap().into_response();
+        assert!(response.back);
+        Ok(())
+    }
+}
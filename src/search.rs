@@ -0,0 +1,65 @@
+//! Incremental reverse search of the history.
+
+/// State for an incremental reverse search.
+pub(crate) struct Search {
+    /// Text to search for.
+    pub query: String,
+    /// Buffer before the search started.
+    pub saved: String,
+    /// Index of the history item that matched.
+    pub index: Option<usize>,
+    /// Whether the last search failed to find a match.
+    pub failed: bool,
+}
+
+impl Search {
+    /// Start a new search.
+    pub fn new(saved: String) -> Self {
+        Self {
+            query: String::new(),
+            saved,
+            index: None,
+            failed: false,
+        }
+    }
+
+    /// Prefix to display while searching.
+    pub fn prefix(&self) -> String {
+        let failed = if self.failed { "failed " } else { "" };
+        format!("({}reverse-i-search)`{}': ", failed, self.query)
+    }
+
+    /// Find the most recent item before an index that contains
+    /// the query returning the index and the byte offset of the match.
+    pub fn find(
+        items: &[String],
+        query: &str,
+        before: usize,
+    ) -> Option<(usize, usize)> {
+        items[..before.min(items.len())]
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, item)| {
+                item.find(query).map(|offset| (index, offset))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_find() {
+        let items = vec![
+            "git status".to_string(),
+            "ls".to_string(),
+            "git stash".to_string(),
+        ];
+        assert_eq!(Some((2, 4)), Search::find(&items, "sta", 3));
+        assert_eq!(Some((0, 4)), Search::find(&items, "sta", 2));
+        assert_eq!(None, Search::find(&items, "sta", 0));
+        assert_eq!(None, Search::find(&items, "cargo", 3));
+    }
+}
@@ -4,305 +4,2274 @@
 //! Its primarily responsbility is for converting strings
 //! to columns representing Unicode graphemes so that we
 //! can handle multi-byte characters correctly.
+//!
+//! The buffer only ever manages the rows that belong to the
+//! prompt, it records the row where the prompt started and
+//! all rendering is relative to that row so that existing
+//! output above the prompt is left untouched.
+//!
+//! The rows that were last rendered are kept so that a redraw
+//! only writes the rows and columns that have changed.
 use anyhow::Result;
 use crossterm::{
     cursor,
+    style::{
+        Attribute, Color, ContentStyle, SetAttribute, SetBackgroundColor,
+        SetForegroundColor,
+    },
     terminal::{Clear, ClearType},
     QueueableCommand,
 };
 use std::borrow::Cow;
+use std::fmt;
 use std::io::Write;
+use std::ops::Range;
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
+use crate::{AsPrefix, Border, Counter, Prefix, Theme};
+
+/// Columns between tab stops.
+const TAB_STOP: usize = 8;
+
+/// Rows to paint for a buffer and the cursor position
+/// relative to the first row.
+struct Layout {
+    rows: Vec<String>,
+    cursor: (u16, u16),
+    /// Number of rows used by the info lines.
+    info_rows: usize,
+}
+
+/// Rows being laid out with the style codes for the current
+/// segment repeated on every row the segment wraps to.
+struct Rows<'s> {
+    rows: Vec<String>,
+    col: usize,
+    width: usize,
+    style: Option<&'s (String, String)>,
+    /// Codes to start and end the highlight.
+    highlight_codes: (&'s str, &'s str),
+    /// Whether the highlight continues on the next row.
+    highlighted: bool,
+    /// Marker in the last column of rows that wrap.
+    marker: Option<&'s str>,
+}
+
+impl<'s> Rows<'s> {
+    fn new(width: usize) -> Self {
+        Self {
+            rows: vec![String::new()],
+            col: 0,
+            width,
+            style: None,
+            highlight_codes: ("", ""),
+            highlighted: false,
+            marker: None,
+        }
+    }
+
+    /// Set the marker for rows that wrap.
+    fn set_marker(&mut self, marker: Option<&'s str>) {
+        self.marker = marker;
+    }
+
+    /// Columns available for text leaving room for the marker.
+    fn text_width(&self) -> usize {
+        match self.marker {
+            Some(marker) => self
+                .width
+                .saturating_sub(UnicodeWidthStr::width(marker))
+                .max(1),
+            None => self.width,
+        }
+    }
+
+    /// Continue on a new row drawing the marker at the end of the row.
+    fn wrap(&mut self) {
+        if let Some(marker) = self.marker {
+            if self.highlighted {
+                self.push_code(self.highlight_codes.1);
+            }
+            let pad = self.text_width().saturating_sub(self.col);
+            self.push_code(&" ".repeat(pad));
+            self.push_code(marker);
+        }
+        self.new_row();
+    }
+
+    /// Append codes that do not occupy any columns.
+    fn push_code(&mut self, code: &str) {
+        self.rows.last_mut().unwrap().push_str(code);
+    }
+
+    /// Start a new segment ending the style of the previous segment.
+    fn set_style(&mut self, style: Option<&'s (String, String)>) {
+        if self.style == style {
+            return;
+        }
+        if let Some((_, off)) = self.style {
+            self.push_code(off);
+        }
+        if let Some((on, _)) = style {
+            self.push_code(on);
+        }
+        self.style = style;
+    }
+
+    /// Set the codes that start and end the highlight.
+    fn set_highlight_codes(&mut self, on: &'s str, off: &'s str) {
+        self.highlight_codes = (on, off);
+    }
+
+    /// Start a highlight that is applied again on new rows.
+    fn start_highlight(&mut self) {
+        self.push_code(self.highlight_codes.0);
+        self.highlighted = true;
+    }
+
+    /// End the highlight.
+    fn end_highlight(&mut self) {
+        if self.highlighted {
+            self.push_code(self.highlight_codes.1);
+            self.highlighted = false;
+        }
+    }
+
+    fn new_row(&mut self) {
+        let style = self.style;
+        let highlighted = self.highlighted;
+        self.end_highlight();
+        self.set_style(None);
+        self.rows.push(String::new());
+        self.col = 0;
+        self.set_style(style);
+        if highlighted {
+            self.start_highlight();
+        }
+    }
+
+    /// Place text wrapping to new rows at the width.
+    fn place(&mut self, s: &str) {
+        self.place_measured(
+            UnicodeSegmentation::graphemes(s, true)
+                .map(|g| (g, UnicodeWidthStr::width(g))),
+        );
+    }
+
+    /// Place graphemes with known widths wrapping to new rows.
+    fn place_measured<'g>(
+        &mut self,
+        graphemes: impl Iterator<Item = (&'g str, usize)>,
+    ) {
+        for (g, cols) in graphemes {
+            if self.col + cols > self.text_width() && self.col > 0 {
+                self.wrap();
+            }
+            self.push_code(g);
+            self.col += cols;
+        }
+    }
+
+    /// Place text passing through the style codes it contains.
+    fn place_styled(&mut self, s: &str) {
+        let mut rest = s;
+        while let Some(start) = rest.find("\x1b[") {
+            self.place(&rest[..start]);
+            let end = rest[start + 2..]
+                .find(|c| ('\x40'..='\x7e').contains(&c))
+                .map(|i| start + 2 + i + 1)
+                .unwrap_or(rest.len());
+            self.push_code(&rest[start..end]);
+            rest = &rest[end..];
+        }
+        self.place(rest);
+    }
+
+    /// Position for the cursor after the placed text.
+    fn mark(&mut self) -> (u16, u16) {
+        if self.col >= self.text_width() {
+            self.wrap();
+        }
+        (self.col as u16, (self.rows.len() - 1) as u16)
+    }
+}
+
+/// Surround rows with a border padding each row to the width
+/// or the widest row when the width is not known.
+fn frame(rows: &mut Vec<String>, border: &Border, width: usize) {
+    let widths: Vec<usize> = rows
+        .iter()
+        .map(|row| UnicodeWidthStr::width(&strip_styles(row)[..]))
+        .collect();
+    let inner = if width == usize::MAX {
+        widths.iter().copied().max().unwrap_or(0)
+    } else {
+        width
+    };
+    let paint = |text: String| match style_codes(&border.style) {
+        Some((on, off)) => format!("{}{}{}", on, text, off),
+        None => text,
+    };
+    let horizontal = border.horizontal.to_string().repeat(inner);
+    let vertical = paint(border.vertical.to_string());
+    for (row, cols) in rows.iter_mut().zip(widths) {
+        let padding = " ".repeat(inner.saturating_sub(cols));
+        *row = format!("{}{}{}{}", vertical, row, padding, vertical);
+    }
+    let top = format!("{}{}{}", border.top_left, horizontal, border.top_right);
+    let bottom = format!(
+        "{}{}{}",
+        border.bottom_left, horizontal, border.bottom_right
+    );
+    rows.insert(0, paint(top));
+    rows.push(paint(bottom));
+}
+
+/// Get the codes to start and end a style or `None`
+/// when the style is empty.
+fn style_codes(style: &ContentStyle) -> Option<(String, String)> {
+    if *style == ContentStyle::default() {
+        return None;
+    }
+    let mut on = String::new();
+    if let Some(color) = style.foreground_color {
+        on.push_str(&SetForegroundColor(color).to_string());
+    }
+    if let Some(color) = style.background_color {
+        on.push_str(&SetBackgroundColor(color).to_string());
+    }
+    for attribute in Attribute::iterator() {
+        if style.attributes.has(attribute) {
+            on.push_str(&SetAttribute(attribute).to_string());
+        }
+    }
+    Some((on, SetAttribute(Attribute::Reset).to_string()))
+}
+
 /// Internal buffer for a string that operates on columns
 /// and rows and may include a prefix to the buffer value.
 pub struct TerminalBuffer<'a> {
-    prefix: &'a str,
+    prefix: Cow<'a, Prefix>,
+    /// Text displayed instead of the prefix.
+    status: Option<String>,
+    /// Lines displayed below the buffer.
+    info: Vec<String>,
+    /// Byte range of the buffer to highlight.
+    highlight: Option<Range<usize>>,
+    /// Foreground color for the prefix and buffer.
+    color: Option<Color>,
+    /// Styles for the prefix, buffer and info lines.
+    theme: Theme,
+    /// Frame drawn around the prefix and buffer.
+    border: Option<Border>,
+    /// Counter for the length of the buffer.
+    counter: Option<Counter>,
+    /// Marker drawn where the buffer wraps to the next row.
+    wrap_marker: Option<char>,
     buffer: String,
-    prefix_cols: usize,
-    buffer_cols: usize,
     echo: Option<char>,
+    /// Nothing is displayed for the buffer.
+    hidden: bool,
+    repeat_prefix: bool,
     size: (u16, u16),
-    position: (u16, u16),
+    /// Row where the prompt begins.
+    origin: u16,
+    /// Byte offset of the cursor in the buffer, always
+    /// on a grapheme boundary.
+    cursor: usize,
+    /// Byte offset of the mark in the buffer.
+    mark: Option<usize>,
+    /// Rows written by the last redraw.
+    rendered: Option<Vec<String>>,
+    /// Only flush when the prompt is finished.
+    defer_flush: bool,
+    /// Treat whitespace as a word of its own when erasing words.
+    stop_at_whitespace: bool,
 }
 
 impl<'a> TerminalBuffer<'a> {
     /// Create a new buffer using the given prefix and mask character.
-    pub fn new(prefix: &'a str, echo: Option<char>) -> Self {
-        let prefix_cols: usize = UnicodeWidthStr::width(prefix);
+    pub fn new<P: AsPrefix + ?Sized>(
+        prefix: &'a P,
+        echo: Option<char>,
+    ) -> Self {
         Self {
-            prefix,
-            prefix_cols,
+            prefix: prefix.as_prefix(),
+            status: None,
+            info: Vec::new(),
+            highlight: None,
+            color: None,
+            theme: Default::default(),
+            border: None,
+            counter: None,
+            wrap_marker: None,
             buffer: String::new(),
-            buffer_cols: 0,
             echo,
+            hidden: false,
+            repeat_prefix: false,
             size: (0, 0),
-            position: (0, 0),
+            origin: 0,
+            cursor: 0,
+            mark: None,
+            rendered: None,
+            defer_flush: false,
+            stop_at_whitespace: false,
         }
     }
 
+    /// Get the number of graphemes in the buffer.
+    pub fn grapheme_count(&self) -> usize {
+        UnicodeSegmentation::graphemes(&self.buffer[..], true).count()
+    }
+
     /// Get the underlying buffer.
     pub fn buffer(&self) -> &str {
         &self.buffer
     }
 
-    /// Get the number of columns for the prefix.
-    pub fn prefix_columns(&self) -> usize {
-        self.prefix_cols
+    /// Get the byte offset of the cursor.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Get the buffer with a marker inserted at the cursor.
+    pub fn with_cursor_marker(&self, marker: &str) -> String {
+        let mut value = self.buffer.clone();
+        value.insert_str(self.cursor, marker);
+        value
     }
 
-    /*
-    /// Get the number of columns for the buffer.
-    pub fn buffer_columns(&self) -> usize {
-        self.buffer_cols
+    /// Set text to display instead of the prefix.
+    #[cfg(feature = "history")]
+    pub fn set_status(&mut self, status: Option<String>) {
+        self.status = status;
     }
-    */
 
-    /// Get the total column width for the prefix and buffer.
-    pub fn columns(&self) -> usize {
-        self.prefix_cols + self.buffer_cols
+    /// Set lines to display below the buffer.
+    #[cfg(any(feature = "history", feature = "completion"))]
+    pub fn set_info(&mut self, info: Vec<String>) {
+        self.info = info;
+    }
+
+    /// Set a byte range of the buffer to highlight.
+    ///
+    /// The highlight is cleared when the buffer is edited.
+    #[cfg(feature = "history")]
+    pub fn set_highlight(&mut self, highlight: Option<Range<usize>>) {
+        self.highlight = highlight;
+    }
+
+    /// Set the buffer value and cursor without drawing.
+    pub fn set_value(&mut self, value: &str, cursor: usize) {
+        self.buffer = value.to_string();
+        self.cursor = cursor;
+        self.mark = None;
+        self.highlight = None;
+    }
+
+    /// Get the mask character.
+    pub fn echo(&self) -> Option<char> {
+        self.echo
+    }
+
+    /// Set the foreground color for the prefix and buffer
+    /// without drawing.
+    pub fn set_color(&mut self, color: Option<Color>) {
+        self.color = color;
+    }
+
+    /// Set the styles for the prefix, buffer and info lines
+    /// without drawing.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// Set the frame drawn around the prefix and buffer
+    /// without drawing.
+    pub fn set_border(&mut self, border: Option<Border>) {
+        self.border = border;
+    }
+
+    /// Set the marker drawn in the last column of rows that
+    /// wrap without drawing.
+    pub fn set_wrap_marker(&mut self, marker: Option<char>) {
+        self.wrap_marker = marker;
+    }
+
+    /// Set the counter displayed below the buffer without drawing.
+    pub fn set_counter(&mut self, counter: Option<Counter>) {
+        self.counter = counter;
+    }
+
+    /// Set the mask character without drawing.
+    pub fn set_echo(&mut self, echo: Option<char>) {
+        self.echo = echo;
+    }
+
+    /// Determine if the value is hidden.
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Set whether the value is hidden without drawing.
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.hidden = hidden;
+    }
+
+    /// Get the terminal size.
+    pub fn size(&self) -> (u16, u16) {
+        self.size
     }
 
     /// Set the terminal size.
     pub fn set_size(&mut self, size: (u16, u16)) {
         self.size = size;
+        // Rows may have been reflowed by the terminal
+        self.rendered = None;
     }
 
-    /// Set the cursor position.
-    pub fn set_position(&mut self, position: (u16, u16)) {
-        self.position = position;
+    /// Set whether flushing is deferred until the prompt is finished.
+    pub fn set_defer_flush(&mut self, defer_flush: bool) {
+        self.defer_flush = defer_flush;
     }
 
-    /// Update the buffer to a new value.
-    fn update(&mut self, value: String) {
-        self.buffer_cols = UnicodeWidthStr::width(&value[..]);
-        self.buffer = value;
+    /// Set whether erasing a word stops at the whitespace
+    /// before the word.
+    pub fn set_stop_at_whitespace(&mut self, stop_at_whitespace: bool) {
+        self.stop_at_whitespace = stop_at_whitespace;
     }
 
-    /// Push a character onto the buffer and write it but do not flush
-    /// the stream.
-    ///
-    /// This should only be used for control characters and newlines
-    /// as it does not respect the masking of visible characters when
-    /// echo has been set.
-    pub fn push<W>(&mut self, writer: &mut W, c: char) -> Result<()>
+    /// Flush the writer unless flushing is deferred.
+    fn flush<W>(&self, writer: &mut W) -> Result<()>
     where
         W: Write,
     {
-        self.buffer.push(c);
-        writer.write(c.to_string().as_bytes())?;
+        if !self.defer_flush {
+            writer.flush()?;
+        }
         Ok(())
     }
 
-    /// Get the graphemes for the buffer.
-    fn graphemes(&self) -> Vec<&str> {
-        UnicodeSegmentation::graphemes(&self.buffer[..], true)
-            .collect::<Vec<&str>>()
-    }
-
-    /// Erase the word before the cursor.
-    pub fn erase_word_before<W>(&mut self, writer: &mut W) -> Result<()>
+    /// Ring the terminal bell.
+    pub fn ring_bell<W>(&self, writer: &mut W) -> Result<()>
     where
         W: Write,
     {
-        if !self.buffer.is_empty() {
-            let (column, row) = self.position;
-            let after_start = column as usize - self.prefix_cols;
-            let before = &self.buffer[0..after_start];
-            let after = &self.buffer[after_start..];
-            let mut words = (before.trim_end()).split_word_bounds();
-            words.next_back();
-            let mut buffer = words.collect::<Vec<&str>>().join("");
-            let new_col: u16 = (self.prefix_cols
-                + UnicodeWidthStr::width(&buffer[..]))
-            .try_into()?;
-            buffer.push_str(after);
-            let position = (new_col, row);
-            self.refresh(writer, buffer, position)?;
+        writer.write_all(b"\x07")?;
+        self.flush(writer)
+    }
+
+    /// Set whether the prefix is written for every line of input.
+    pub fn set_repeat_prefix(&mut self, repeat_prefix: bool) {
+        self.repeat_prefix = repeat_prefix;
+    }
+
+    /// Replace a byte range of the buffer with text and
+    /// return the removed text.
+    ///
+    /// All edits are performed using this function so that
+    /// the mark follows the text it was set on.
+    fn splice(&mut self, range: Range<usize>, text: &str) -> String {
+        if let Some(mark) = self.mark {
+            self.mark = Some(if mark >= range.end {
+                mark - range.len() + text.len()
+            } else if mark > range.start {
+                range.start
+            } else {
+                mark
+            });
         }
-        Ok(())
+        self.highlight = None;
+        let removed = self.buffer.drain(range.clone()).collect();
+        self.buffer.insert_str(range.start, text);
+        removed
     }
 
-    /// Erase a number of columns before the cursor.
-    pub fn erase_before<W>(
+    /// Start the prompt with the cursor at the given position.
+    ///
+    /// When the cursor is not at the beginning of a line a
+    /// newline is written so the prompt always starts on a
+    /// fresh line below any existing output.
+    pub fn start<W>(
         &mut self,
         writer: &mut W,
-        amount: usize,
+        position: (u16, u16),
     ) -> Result<()>
     where
         W: Write,
     {
-        self.erase(writer, amount, true)
+        let (column, row) = position;
+        let (_, height) = self.size;
+        self.origin = if column > 0 {
+            writer.write_all(b"\r\n")?;
+            if height > 0 {
+                (row + 1).min(height - 1)
+            } else {
+                row + 1
+            }
+        } else {
+            row
+        };
+        self.rendered = None;
+        self.redraw(writer)
     }
 
-    /// Erase a number of columns after the cursor.
-    pub fn erase_after<W>(
-        &mut self,
-        writer: &mut W,
-        amount: usize,
-    ) -> Result<()>
+    /// Finish the prompt moving the cursor to the line
+    /// after the last row of the prompt.
+    ///
+    /// Returns the position of the cursor.
+    pub fn finish<W>(&mut self, writer: &mut W) -> Result<(u16, u16)>
     where
         W: Write,
     {
-        self.erase(writer, amount, false)
+        let rows = self.layout().rows.len() as u16;
+        let row = self.origin + rows - 1;
+        writer.queue(cursor::MoveTo(0, row))?;
+        writer.write_all(b"\r\n")?;
+        writer.flush()?;
+        let (_, height) = self.size;
+        if height > 0 {
+            Ok((0, (row + 1).min(height - 1)))
+        } else {
+            Ok((0, row + 1))
+        }
     }
 
-    /// Erase a number of columns before or after the cursor.
-    fn erase<W>(
-        &mut self,
-        writer: &mut W,
-        amount: usize,
-        before: bool,
-    ) -> Result<()>
-    where
-        W: Write,
-    {
-        let graphemes = self.graphemes();
-        if graphemes.len() > 0 {
-            // Cursor position relative to start of the buffer
-            let (column, row) = self.position;
-            let (before_end, after_start, new_col) = if before {
-                let after_start = column as usize - self.prefix_columns();
-                let before_end = if after_start >= amount {
-                    after_start - amount
-                } else {
-                    amount
+    /// Get the display string for a grapheme in the buffer.
+    ///
+    /// When masking each grapheme is displayed as a single mask
+    /// character regardless of the width of the grapheme.
+    fn display<'g>(&self, grapheme: &'g str) -> Cow<'g, str> {
+        if let Some(echo) = &self.echo {
+            Cow::Owned(echo.to_string())
+        } else {
+            Cow::Borrowed(grapheme)
+        }
+    }
+
+    /// Compute the rows for the prefix and buffer wrapped
+    /// to the terminal width.
+    fn layout(&self) -> Layout {
+        let width = if self.size.0 == 0 {
+            usize::MAX
+        } else if self.border.is_some() {
+            // Leave room for the left and right border
+            (self.size.0 as usize).saturating_sub(2).max(1)
+        } else {
+            self.size.0 as usize
+        };
+
+        // The color for a submitted line replaces the theme
+        let (prefix_style, value_style) = match self.color {
+            Some(color) => {
+                let style = ContentStyle {
+                    foreground_color: Some(color),
+                    ..Default::default()
                 };
-                let new_col = self.prefix_cols + (after_start - amount);
-                (before_end, after_start, new_col)
+                (style, style)
+            }
+            None => (self.theme.prefix, self.theme.value),
+        };
+        let prefix_style = style_codes(&prefix_style);
+        let value_style = style_codes(&value_style);
+        let info_style = style_codes(&self.theme.info);
+
+        // Style codes do not occupy any columns
+        let highlight_on = SetAttribute(Attribute::Underlined).to_string();
+        let highlight_off = SetAttribute(Attribute::NoUnderline).to_string();
+
+        let marker = self.wrap_marker.map(String::from);
+        let mut rows = Rows::new(width);
+        rows.set_marker(marker.as_deref());
+        rows.set_highlight_codes(&highlight_on, &highlight_off);
+        let mut cursor = None;
+
+        let highlight = |rows: &mut Rows<'_>, index: usize| {
+            if let Some(range) = &self.highlight {
+                if index == range.start {
+                    rows.start_highlight();
+                } else if index == range.end {
+                    rows.end_highlight();
+                }
+            }
+        };
+
+        rows.set_style(prefix_style.as_ref());
+        match &self.status {
+            Some(status) => rows.place(status),
+            None => rows.place_measured(self.prefix.graphemes()),
+        }
+        rows.set_style(value_style.as_ref());
+        // Hidden values leave the cursor after the prefix
+        let value = if self.hidden { "" } else { &self.buffer[..] };
+        for (index, grapheme) in
+            UnicodeSegmentation::grapheme_indices(value, true)
+        {
+            if index == self.cursor {
+                cursor = Some(rows.mark());
+            }
+            highlight(&mut rows, index);
+            if grapheme == "\n" || grapheme == "\r\n" {
+                rows.new_row();
+                if self.repeat_prefix {
+                    rows.set_style(prefix_style.as_ref());
+                    rows.place_measured(self.prefix.graphemes());
+                    rows.set_style(value_style.as_ref());
+                }
+            } else if grapheme == "\t" && self.echo.is_none() {
+                // Expand to the next tab stop so cursor math is correct
+                let spaces = TAB_STOP - (rows.col % TAB_STOP);
+                rows.place(&" ".repeat(spaces));
             } else {
-                let before_end = column as usize - self.prefix_columns();
-                let after_start = if before_end + amount <= graphemes.len() {
-                    before_end + amount
-                } else {
-                    graphemes.len()
-                };
-                (before_end, after_start, column as usize)
-            };
+                rows.place(&self.display(grapheme));
+            }
+        }
 
-            let before_range = 0..before_end;
-            let after_range = after_start..self.buffer_cols;
+        highlight(&mut rows, value.len());
+
+        let cursor = match cursor {
+            Some(cursor) => cursor,
+            None => rows.mark(),
+        };
+        rows.set_style(None);
 
-            let mut new_buf = String::new();
-            new_buf.push_str(&graphemes[before_range].join(""));
-            new_buf.push_str(&graphemes[after_range].join(""));
+        let cursor = match &self.border {
+            Some(border) => {
+                frame(&mut rows.rows, border, width);
+                (cursor.0 + 1, cursor.1 + 1)
+            }
+            None => cursor,
+        };
 
-            self.refresh(writer, new_buf, (new_col.try_into()?, row))?;
+        let content_rows = rows.rows.len();
+        rows.set_marker(None);
+        for line in &self.info {
+            rows.new_row();
+            rows.set_style(info_style.as_ref());
+            rows.place_styled(line);
+            rows.set_style(None);
+        }
+        if let (Some(counter), false) = (&self.counter, self.hidden) {
+            rows.new_row();
+            rows.set_style(info_style.as_ref());
+            rows.place(&counter.text(&self.buffer));
+            rows.set_style(None);
         }
+        let rows = rows.rows;
+        let info_rows = rows.len() - content_rows;
 
-        Ok(())
+        Layout {
+            rows,
+            cursor,
+            info_rows,
+        }
     }
 
-    /// Get a visible representation of the buffer.
-    pub fn visible(&'a self) -> Cow<'a, str> {
-        if let Some(echo) = &self.echo {
-            let masked = echo.to_string().repeat(self.buffer_cols);
-            Cow::Owned(masked)
+    /// Redraw the prefix and buffer and move the cursor
+    /// to the current position.
+    ///
+    /// Only the rows owned by the prompt are cleared and when
+    /// the prompt was already rendered only the changes to the
+    /// rows are written.
+    pub fn redraw<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let layout = self.layout();
+        if let Some(rendered) = self.rendered.take() {
+            self.draw_changes(writer, &rendered, &layout.rows)?;
         } else {
-            Cow::Borrowed(&self.buffer)
+            writer.queue(cursor::MoveTo(0, self.origin))?;
+            writer.queue(Clear(ClearType::FromCursorDown))?;
+            for (index, row) in layout.rows.iter().enumerate() {
+                if index > 0 {
+                    writer.write_all(b"\r\n")?;
+                }
+                writer.write_all(row.as_bytes())?;
+            }
         }
-    }
 
-    /// Write bytes to the stream and flush.
-    fn write_bytes(&self, writer: &mut dyn Write, bytes: &[u8]) -> Result<()> {
-        writer.write(bytes)?;
-        writer.flush()?;
+        // Writing past the last row scrolls the terminal
+        let (_, height) = self.size;
+        let rows = layout.rows.len() as u16;
+        if height > 0 && self.origin + rows > height {
+            self.origin = height.saturating_sub(rows);
+        }
+
+        let (col, row) = layout.cursor;
+        writer.queue(cursor::MoveTo(col, self.origin + row))?;
+        self.flush(writer)?;
+        self.rendered = Some(layout.rows);
         Ok(())
     }
 
-    /// Write the prefix and flush the stream.
-    pub fn write_prefix<W>(&mut self, writer: &mut W) -> Result<()>
+    /// Write the differences between the rendered rows and
+    /// the new rows.
+    ///
+    /// Changed rows are rewritten from the first grapheme that
+    /// differs; a row containing style codes is rewritten from
+    /// the first code as the codes do not occupy any columns.
+    fn draw_changes<W>(
+        &mut self,
+        writer: &mut W,
+        rendered: &[String],
+        rows: &[String],
+    ) -> Result<()>
     where
         W: Write,
     {
-        self.write_bytes(writer, self.prefix.as_bytes())
+        let (_, height) = self.size;
+        for (index, row) in rows.iter().enumerate() {
+            let line = self.origin + index as u16;
+            if let Some(previous) = rendered.get(index) {
+                if previous == row {
+                    continue;
+                }
+                let (offset, col) = common_prefix(previous, row);
+                writer.queue(cursor::MoveTo(col, line))?;
+                writer.write_all(&row.as_bytes()[offset..])?;
+                writer.queue(Clear(ClearType::UntilNewLine))?;
+            } else {
+                // New lines scroll the terminal when necessary
+                writer.queue(cursor::MoveTo(0, line - 1))?;
+                writer.write_all(b"\r\n")?;
+                if height > 0 && line >= height {
+                    self.origin -= 1;
+                }
+                writer.write_all(row.as_bytes())?;
+            }
+        }
+        if rendered.len() > rows.len() {
+            writer.queue(cursor::MoveTo(0, self.origin + rows.len() as u16))?;
+            writer.queue(Clear(ClearType::FromCursorDown))?;
+        }
+        Ok(())
     }
 
-    /// Redraw the prefix and buffer moving the cursor
-    /// to the given position.
-    pub fn redraw<W>(&self, writer: &mut W, position: (u16, u16)) -> Result<()>
+    /// Move the terminal cursor to the current position.
+    fn place_cursor<W>(&self, writer: &mut W) -> Result<()>
     where
         W: Write,
     {
-        let (col, row) = position;
-        writer.queue(cursor::MoveTo(0, row))?;
-        writer.queue(Clear(ClearType::CurrentLine))?;
-        writer.write(self.prefix.as_bytes())?;
-        writer.write(self.visible().as_ref().as_bytes())?;
-        writer.queue(cursor::MoveTo(col, row))?;
-        writer.flush()?;
+        let (col, row) = self.layout().cursor;
+        writer.queue(cursor::MoveTo(col, self.origin + row))?;
+        self.flush(writer)?;
         Ok(())
     }
 
-    /// Redraw the prefix and buffer moving the cursor
-    /// to the given position.
+    /// Replace the buffer with a new value placing the cursor
+    /// at the end and redraw.
     pub fn refresh<W, S: AsRef<str>>(
         &mut self,
         writer: &mut W,
         buf: S,
-        position: (u16, u16),
     ) -> Result<()>
     where
         W: Write,
     {
-        self.update(buf.as_ref().to_string());
-        self.redraw(writer, position)
+        self.buffer = buf.as_ref().to_string();
+        self.cursor = self.buffer.len();
+        self.mark = None;
+        self.redraw(writer)
     }
 
-    // Write a character to the line.
-    pub fn write_char<W>(&mut self, writer: &mut W, c: char) -> Result<()>
+    /// Clear the entire screen and redraw the prompt
+    /// at the top of the terminal.
+    pub fn clear_screen<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        writer.queue(Clear(ClearType::All))?;
+        self.origin = 0;
+        self.rendered = None;
+        self.redraw(writer)
+    }
+
+    /// Insert text at the cursor.
+    pub fn insert<W>(&mut self, writer: &mut W, text: &str) -> Result<()>
+    where
+        W: Write,
+    {
+        self.splice(self.cursor..self.cursor, text);
+        self.cursor += text.len();
+        self.redraw(writer)
+    }
+
+    /// Insert text that may contain newlines at the cursor.
+    ///
+    /// Line endings are normalized so that every line of the
+    /// text becomes a logical line of the buffer.
+    pub fn insert_lines<W>(&mut self, writer: &mut W, text: &str) -> Result<()>
+    where
+        W: Write,
+    {
+        self.insert(writer, &normalize_newlines(text))
+    }
+
+    /// Get the number of rows for the prefix and buffer
+    /// at the current size.
+    pub fn row_count(&self) -> usize {
+        let layout = self.layout();
+        layout.rows.len() - layout.info_rows
+    }
+
+    /// Determine if the prefix and buffer wrapped to more rows
+    /// than there are logical lines at the current size.
+    pub fn is_wrapped(&self) -> bool {
+        let border_rows = if self.border.is_some() { 2 } else { 0 };
+        self.row_count() - border_rows > self.lines().count()
+    }
+
+    /// Get the rows for the prefix and buffer as painted by
+    /// a redraw joined with newlines and without styling.
+    pub fn visible_line(&self) -> String {
+        let layout = self.layout();
+        let content_rows = layout.rows.len() - layout.info_rows;
+        layout.rows[..content_rows]
+            .iter()
+            .map(|row| strip_styles(row))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Get the logical lines of the buffer.
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.buffer.split('\n')
+    }
+
+    /// Replace the text in a byte range with new text placing
+    /// the cursor after the replacement.
+    pub fn replace<W>(
+        &mut self,
+        writer: &mut W,
+        range: Range<usize>,
+        text: &str,
+    ) -> Result<()>
     where
         W: Write,
     {
-        let graphemes = self.graphemes();
+        self.cursor = range.start + text.len();
+        self.splice(range, text);
+        self.redraw(writer)
+    }
 
-        let (col, row) = self.position;
-        let pos = col as usize - self.prefix_cols;
-        let char_str = c.to_string();
+    /// Replace the spaces and tabs around the cursor
+    /// with new text placing the cursor after the replacement.
+    pub fn replace_horizontal_space<W>(
+        &mut self,
+        writer: &mut W,
+        text: &str,
+    ) -> Result<()>
+    where
+        W: Write,
+    {
+        let is_blank = |g: &str| g == " " || g == "\t";
+        let start = UnicodeSegmentation::grapheme_indices(
+            &self.buffer[..self.cursor],
+            true,
+        )
+        .rev()
+        .take_while(|(_, g)| is_blank(g))
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(self.cursor);
+        let end = self.cursor
+            + UnicodeSegmentation::graphemes(&self.buffer[self.cursor..], true)
+                .take_while(|g| is_blank(g))
+                .map(str::len)
+                .sum::<usize>();
+        self.replace(writer, start..end, text)
+    }
 
-        // Appending to the end
-        let (before, after) = if pos as usize == self.buffer.len() {
-            (&graphemes[..], &graphemes[graphemes.len()..])
+    /// Replace the graphemes of the current line displayed in a
+    /// range of columns with new text placing the cursor after
+    /// the replacement.
+    ///
+    /// Columns are counted like [TerminalBuffer::move_to_column],
+    /// a wide grapheme partially inside the range is replaced and
+    /// an empty range inserts before the grapheme at the column.
+    ///
+    /// This is used to expand abbreviations; completions replace
+    /// byte ranges as a candidate may start on a previous line.
+    pub fn replace_range<W>(
+        &mut self,
+        writer: &mut W,
+        start_col: usize,
+        end_col: usize,
+        text: &str,
+    ) -> Result<()>
+    where
+        W: Write,
+    {
+        let end = self.line_end();
+        let graphemes = self.line_columns();
+        let start = graphemes
+            .iter()
+            .find(|(_, col, cols)| col + cols > start_col)
+            .map(|(offset, _, _)| *offset)
+            .unwrap_or(end);
+        let stop = if start_col < end_col {
+            graphemes
+                .iter()
+                .find(|(_, col, _)| *col >= end_col)
+                .map(|(offset, _, _)| *offset)
+                .unwrap_or(end)
         } else {
-            let before = &graphemes[0..pos as usize];
-            let after = &graphemes[pos as usize..];
-            (before, after)
+            start
         };
+        self.replace(writer, start..stop, text)
+    }
 
-        // Prepare new line buffer
-        let mut new_buf = String::new();
-        new_buf.push_str(&before.join(""));
-        new_buf.push_str(&char_str[..]);
-        new_buf.push_str(&after.join(""));
-
-        // Store the updated buffer
-        self.update(new_buf);
+    /// Get the number of columns between the beginning of the
+    /// line and the cursor.
+    pub fn cursor_column(&self) -> usize {
+        self.column_of(self.cursor)
+    }
 
-        let new_pos = ((self.prefix_cols + pos + 1) as u16, row);
-        self.redraw(writer, new_pos)?;
+    /// Get the number of columns between the beginning of the
+    /// current line and a byte offset on the line.
+    pub fn column_of(&self, offset: usize) -> usize {
+        self.line_columns()
+            .iter()
+            .take_while(|(index, _, _)| *index < offset)
+            .map(|(_, _, cols)| cols)
+            .sum()
+    }
 
-        Ok(())
+    /// Byte offset, first column and width of each grapheme on
+    /// the current line.
+    ///
+    /// Columns start at the beginning of the line and tabs expand
+    /// to the next tab stop as they are drawn after the prefix.
+    fn line_columns(&self) -> Vec<(usize, usize, usize)> {
+        let begin = self.line_begin();
+        let end = self.line_end();
+        let indent = if begin == 0 {
+            match &self.status {
+                Some(status) => UnicodeWidthStr::width(&status[..]),
+                None => self.prefix.columns(),
+            }
+        } else if self.repeat_prefix {
+            self.prefix.columns()
+        } else {
+            0
+        };
+        let mut col = 0;
+        UnicodeSegmentation::grapheme_indices(&self.buffer[begin..end], true)
+            .map(|(index, grapheme)| {
+                let cols = if grapheme == "\t" && self.echo.is_none() {
+                    TAB_STOP - ((indent + col) % TAB_STOP)
+                } else {
+                    UnicodeWidthStr::width(grapheme)
+                };
+                col += cols;
+                (begin + index, col - cols, cols)
+            })
+            .collect()
     }
 
-    // Calculate the end position for a value.
-    pub fn end_pos(&self, value: &str) -> (u16, u16) {
-        let (_col, row) = self.position;
-        let (w, _h) = self.size;
-        let remainder = w as usize - self.prefix_cols;
-        // Fits without wrapping
-        if value.len() < remainder {
-            let len = UnicodeWidthStr::width(value);
-            let new_col = (self.prefix_cols + len) as u16;
-            (new_col, row)
+    /// Add or remove a prefix at the beginning of the current
+    /// line keeping the cursor at the same place in the line.
+    pub fn toggle_line_prefix<W>(
+        &mut self,
+        writer: &mut W,
+        prefix: &str,
+    ) -> Result<()>
+    where
+        W: Write,
+    {
+        let begin = self.line_begin();
+        if self.buffer[begin..].starts_with(prefix) {
+            let end = begin + prefix.len();
+            self.cursor = if self.cursor >= end {
+                self.cursor - prefix.len()
+            } else {
+                begin
+            };
+            self.splice(begin..end, "");
         } else {
-            todo!("calculate with long wrapped value");
+            self.cursor += prefix.len();
+            self.splice(begin..begin, prefix);
         }
+        self.redraw(writer)
     }
-}
 
-impl Into<String> for TerminalBuffer<'_> {
-    fn into(self) -> String {
-        self.buffer
+    /// Sort the lines of the buffer.
+    pub fn sort_lines<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.rearrange_lines(writer, |lines| lines.sort())
+    }
+
+    /// Remove lines that repeat an earlier line of the buffer.
+    pub fn dedup_lines<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.rearrange_lines(writer, |lines| {
+            let mut seen = std::collections::HashSet::new();
+            lines.retain(|line| seen.insert(*line));
+        })
+    }
+
+    /// Rearrange the lines of the buffer keeping the cursor
+    /// on the first line with the text of the current line.
+    fn rearrange_lines<W, F>(&mut self, writer: &mut W, f: F) -> Result<()>
+    where
+        W: Write,
+        F: FnOnce(&mut Vec<&str>),
+    {
+        let begin = self.line_begin();
+        let current = self.buffer[begin..self.line_end()].to_string();
+        let column = self.cursor - begin;
+
+        let mut lines: Vec<&str> = self.buffer.split('\n').collect();
+        f(&mut lines);
+        let mut offset = 0;
+        for line in lines.iter() {
+            if *line == current {
+                break;
+            }
+            offset += line.len() + 1;
+        }
+        let text = lines.join("\n");
+
+        self.mark = None;
+        self.cursor = (offset + column).min(text.len());
+        let end = self.buffer.len();
+        self.splice(0..end, &text);
+        self.redraw(writer)
+    }
+
+    /// Write a character to the line.
+    pub fn write_char<W>(&mut self, writer: &mut W, c: char) -> Result<()>
+    where
+        W: Write,
+    {
+        self.insert(writer, &c.to_string())
+    }
+
+    /// Byte offset of the grapheme before the cursor.
+    fn previous_boundary(&self) -> Option<usize> {
+        UnicodeSegmentation::graphemes(&self.buffer[..self.cursor], true)
+            .next_back()
+            .map(|g| self.cursor - g.len())
+    }
+
+    /// Byte offset of the grapheme after the cursor.
+    fn next_boundary(&self) -> Option<usize> {
+        UnicodeSegmentation::graphemes(&self.buffer[self.cursor..], true)
+            .next()
+            .map(|g| self.cursor + g.len())
+    }
+
+    /// Swap the grapheme at the cursor with the next grapheme
+    /// moving the cursor forward one grapheme.
+    ///
+    /// Returns whether the graphemes were swapped, nothing is
+    /// swapped when either grapheme is missing or a newline.
+    pub fn transpose_forward<W>(&mut self, writer: &mut W) -> Result<bool>
+    where
+        W: Write,
+    {
+        let mut graphemes =
+            UnicodeSegmentation::graphemes(&self.buffer[self.cursor..], true);
+        let (first, second) = match (graphemes.next(), graphemes.next()) {
+            (Some(first), Some(second))
+                if !first.ends_with('\n') && !second.ends_with('\n') =>
+            {
+                (first.to_string(), second.to_string())
+            }
+            _ => return Ok(false),
+        };
+        let range = self.cursor..self.cursor + first.len() + second.len();
+        let cursor = self.cursor + second.len();
+        self.splice(range, &format!("{}{}", second, first));
+        self.cursor = cursor;
+        self.redraw(writer)?;
+        Ok(true)
+    }
+
+    /// Byte offset for the beginning of the current line.
+    fn line_begin(&self) -> usize {
+        self.buffer[..self.cursor]
+            .rfind('\n')
+            .map(|i| i + 1)
+            .unwrap_or(0)
+    }
+
+    /// Byte offset for the end of the current line.
+    fn line_end(&self) -> usize {
+        self.buffer[self.cursor..]
+            .find('\n')
+            .map(|i| self.cursor + i)
+            .unwrap_or(self.buffer.len())
+    }
+
+    /// Byte range of the word under the cursor.
+    ///
+    /// When the cursor is directly after a word that word is used.
+    pub fn word_at_cursor(&self) -> Option<Range<usize>> {
+        let is_word = |s: &str| s.chars().any(char::is_alphanumeric);
+        let mut previous = None;
+        for (start, word) in self.buffer.split_word_bound_indices() {
+            let end = start + word.len();
+            if start <= self.cursor && self.cursor < end && is_word(word) {
+                return Some(start..end);
+            }
+            if end == self.cursor && is_word(word) {
+                previous = Some(start..end);
+            }
+        }
+        previous
+    }
+
+    /// Move the cursor to the end of the next word or to the
+    /// end of the buffer.
+    pub fn move_word_forward<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let is_word = |s: &str| s.chars().any(char::is_alphanumeric);
+        self.cursor = self
+            .buffer
+            .split_word_bound_indices()
+            .map(|(start, word)| (start + word.len(), word))
+            .find(|(end, word)| *end > self.cursor && is_word(word))
+            .map(|(end, _)| end)
+            .unwrap_or(self.buffer.len());
+        self.place_cursor(writer)
+    }
+
+    /// Move the cursor to the start of the previous word or to
+    /// the beginning of the buffer.
+    pub fn move_word_backward<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let is_word = |s: &str| s.chars().any(char::is_alphanumeric);
+        self.cursor = self.buffer[..self.cursor]
+            .split_word_bound_indices()
+            .rev()
+            .find(|(_, word)| is_word(word))
+            .map(|(start, _)| start)
+            .unwrap_or(0);
+        self.place_cursor(writer)
+    }
+
+    /// Byte offset for the start of the whitespace delimited
+    /// word before the cursor.
+    pub fn word_start(&self) -> usize {
+        let before = &self.buffer[..self.cursor];
+        before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map(|(i, c)| i + c.len_utf8())
+            .unwrap_or(0)
+    }
+
+    /// Set the mark at the cursor.
+    pub fn set_mark(&mut self) {
+        self.mark = Some(self.cursor);
+    }
+
+    /// Byte range between the mark and the cursor.
+    pub fn region(&self) -> Option<Range<usize>> {
+        self.mark
+            .map(|mark| mark.min(self.cursor)..mark.max(self.cursor))
+    }
+
+    /// Select the entire buffer by setting the mark at the
+    /// beginning and moving the cursor to the end.
+    pub fn select_all<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.mark = Some(0);
+        self.cursor = self.buffer.len();
+        self.place_cursor(writer)
+    }
+
+    /// Erase the region and clear the mark.
+    ///
+    /// Returns the erased text.
+    pub fn erase_region<W>(&mut self, writer: &mut W) -> Result<String>
+    where
+        W: Write,
+    {
+        let mut erased = String::new();
+        if let Some(region) = self.region() {
+            self.cursor = region.start;
+            erased = self.splice(region, "");
+            self.mark = None;
+            self.redraw(writer)?;
+        }
+        Ok(erased)
+    }
+
+    /// Wrap the region in a pair of delimiters placing the cursor
+    /// after the closing delimiter and clearing the mark.
+    ///
+    /// Without a region an empty pair is inserted with the cursor
+    /// between the delimiters.
+    pub fn wrap_region<W>(
+        &mut self,
+        writer: &mut W,
+        open: char,
+        close: char,
+    ) -> Result<()>
+    where
+        W: Write,
+    {
+        match self.region().filter(|region| !region.is_empty()) {
+            Some(region) => {
+                let text = format!(
+                    "{}{}{}",
+                    open,
+                    &self.buffer[region.clone()],
+                    close
+                );
+                self.mark = None;
+                self.replace(writer, region, &text)
+            }
+            None => {
+                let cursor = self.cursor;
+                let pair = format!("{}{}", open, close);
+                self.splice(cursor..cursor, &pair);
+                self.cursor = cursor + open.len_utf8();
+                self.redraw(writer)
+            }
+        }
+    }
+
+    /// Move the cursor one grapheme to the left.
+    pub fn move_left<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        if let Some(cursor) = self.previous_boundary() {
+            self.cursor = cursor;
+            self.place_cursor(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Move the cursor one grapheme to the right.
+    pub fn move_right<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        if let Some(cursor) = self.next_boundary() {
+            self.cursor = cursor;
+            self.place_cursor(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Move the cursor to the beginning of the line.
+    pub fn move_to_line_begin<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.cursor = self.line_begin();
+        self.place_cursor(writer)
+    }
+
+    /// Move the cursor to a display column counted from the
+    /// beginning of the line.
+    ///
+    /// Tabs expand to the next tab stop as they are drawn, the
+    /// cursor stops before the grapheme that covers the column
+    /// and at the end of the line for a column beyond the end.
+    pub fn move_to_column<W>(
+        &mut self,
+        writer: &mut W,
+        column: usize,
+    ) -> Result<()>
+    where
+        W: Write,
+    {
+        self.cursor = self
+            .line_columns()
+            .iter()
+            .find(|(_, col, cols)| col + cols > column)
+            .map(|(offset, _, _)| *offset)
+            .unwrap_or(self.line_end());
+        self.place_cursor(writer)
+    }
+
+    /// Move the cursor to the end of the line.
+    pub fn move_to_line_end<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.cursor = self.line_end();
+        self.place_cursor(writer)
+    }
+
+    /// Move the cursor to the blank line before the paragraph
+    /// or to the beginning of the buffer.
+    pub fn move_to_previous_paragraph<W>(
+        &mut self,
+        writer: &mut W,
+    ) -> Result<()>
+    where
+        W: Write,
+    {
+        let lines = self.line_offsets();
+        let current =
+            lines.iter().rposition(|(start, _)| *start <= self.cursor);
+        let mut rest = lines[..=current.unwrap_or(0)]
+            .iter()
+            .rev()
+            .copied()
+            .skip_while(|(_, blank)| *blank)
+            .skip_while(|(_, blank)| !*blank);
+        self.cursor = rest.next().map(|(start, _)| start).unwrap_or(0);
+        self.place_cursor(writer)
+    }
+
+    /// Move the cursor to the blank line after the paragraph
+    /// or to the end of the buffer.
+    pub fn move_to_next_paragraph<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let lines = self.line_offsets();
+        let current =
+            lines.iter().rposition(|(start, _)| *start <= self.cursor);
+        let mut rest = lines[current.unwrap_or(0)..]
+            .iter()
+            .copied()
+            .skip_while(|(_, blank)| *blank)
+            .skip_while(|(_, blank)| !*blank);
+        self.cursor = rest
+            .next()
+            .map(|(start, _)| start)
+            .unwrap_or(self.buffer.len());
+        self.place_cursor(writer)
+    }
+
+    /// Byte offset for the beginning of each line and
+    /// whether the line is blank.
+    fn line_offsets(&self) -> Vec<(usize, bool)> {
+        let mut start = 0;
+        self.buffer
+            .split('\n')
+            .map(|line| {
+                let offset = start;
+                start += line.len() + 1;
+                (offset, line.trim().is_empty())
+            })
+            .collect()
+    }
+
+    /// Erase the word before the cursor.
+    ///
+    /// Whitespace before the cursor is erased together with the
+    /// word unless erasing stops at whitespace, then the run of
+    /// whitespace is erased on its own.
+    ///
+    /// Returns the erased text.
+    pub fn erase_word_before<W>(&mut self, writer: &mut W) -> Result<String>
+    where
+        W: Write,
+    {
+        let before = &self.buffer[..self.cursor];
+        let before = if self.stop_at_whitespace {
+            before
+        } else {
+            before.trim_end()
+        };
+        let start = before
+            .split_word_bounds()
+            .next_back()
+            .map(|word| before.len() - word.len())
+            .unwrap_or(0);
+        let amount = UnicodeSegmentation::graphemes(
+            &self.buffer[start..self.cursor],
+            true,
+        )
+        .count();
+        self.erase_before(writer, amount)
+    }
+
+    /// Erase the whitespace delimited word before the cursor
+    /// including any whitespace between the word and the cursor.
+    ///
+    /// Unlike erasing a word punctuation does not delimit a word.
+    ///
+    /// Returns the erased text.
+    pub fn erase_whitespace_word_before<W>(
+        &mut self,
+        writer: &mut W,
+    ) -> Result<String>
+    where
+        W: Write,
+    {
+        let before = self.buffer[..self.cursor].trim_end();
+        let start = before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map(|(i, c)| i + c.len_utf8())
+            .unwrap_or(0);
+        let amount = UnicodeSegmentation::graphemes(
+            &self.buffer[start..self.cursor],
+            true,
+        )
+        .count();
+        self.erase_before(writer, amount)
+    }
+
+    /// Erase to the beginning of the line.
+    ///
+    /// Returns the erased text.
+    pub fn erase_to_line_begin<W>(&mut self, writer: &mut W) -> Result<String>
+    where
+        W: Write,
+    {
+        let amount = UnicodeSegmentation::graphemes(
+            &self.buffer[self.line_begin()..self.cursor],
+            true,
+        )
+        .count();
+        self.erase_before(writer, amount)
+    }
+
+    /// Erase to the end of the line.
+    ///
+    /// Returns the erased text.
+    pub fn erase_to_line_end<W>(&mut self, writer: &mut W) -> Result<String>
+    where
+        W: Write,
+    {
+        let amount = UnicodeSegmentation::graphemes(
+            &self.buffer[self.cursor..self.line_end()],
+            true,
+        )
+        .count();
+        self.erase_after(writer, amount)
+    }
+
+    /// Erase from the cursor up to but not including the next
+    /// occurrence of a character on the line.
+    ///
+    /// The grapheme at the cursor is skipped when searching, returns
+    /// the erased text or `None` when the character is not found.
+    pub fn erase_to_char<W>(
+        &mut self,
+        writer: &mut W,
+        c: char,
+    ) -> Result<Option<String>>
+    where
+        W: Write,
+    {
+        let rest = &self.buffer[self.cursor..self.line_end()];
+        let target = c.to_string();
+        let amount = UnicodeSegmentation::graphemes(rest, true)
+            .skip(1)
+            .position(|g| g == target)
+            .map(|position| position + 1);
+        match amount {
+            Some(amount) => Ok(Some(self.erase_after(writer, amount)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Erase a number of graphemes before the cursor.
+    ///
+    /// Returns the erased text.
+    pub fn erase_before<W>(
+        &mut self,
+        writer: &mut W,
+        amount: usize,
+    ) -> Result<String>
+    where
+        W: Write,
+    {
+        let start = UnicodeSegmentation::grapheme_indices(
+            &self.buffer[..self.cursor],
+            true,
+        )
+        .rev()
+        .take(amount)
+        .last()
+        .map(|(i, _)| i);
+
+        let mut erased = String::new();
+        if let Some(start) = start {
+            erased = self.splice(start..self.cursor, "");
+            self.cursor = start;
+            self.redraw(writer)?;
+        }
+        Ok(erased)
+    }
+
+    /// Erase a number of graphemes after the cursor.
+    ///
+    /// Returns the erased text.
+    pub fn erase_after<W>(
+        &mut self,
+        writer: &mut W,
+        amount: usize,
+    ) -> Result<String>
+    where
+        W: Write,
+    {
+        let end =
+            UnicodeSegmentation::graphemes(&self.buffer[self.cursor..], true)
+                .take(amount)
+                .map(|g| g.len())
+                .sum::<usize>();
+
+        let mut erased = String::new();
+        if end > 0 {
+            erased = self.splice(self.cursor..self.cursor + end, "");
+            self.redraw(writer)?;
+        }
+        Ok(erased)
+    }
+}
+
+/// Convert carriage returns and Windows line endings to newlines.
+pub(crate) fn normalize_newlines(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Remove the escape sequences used for styling from a row.
+fn strip_styles(row: &str) -> String {
+    let mut stripped = String::with_capacity(row.len());
+    let mut chars = row.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip to the final byte of the control sequence
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+/// Find the byte offset and column where two rows first differ.
+fn common_prefix(a: &str, b: &str) -> (usize, u16) {
+    let mut offset = 0;
+    let mut col = 0;
+    for (g, other) in UnicodeSegmentation::graphemes(a, true)
+        .zip(UnicodeSegmentation::graphemes(b, true))
+    {
+        if g != other || g.starts_with('\x1b') {
+            break;
+        }
+        offset += g.len();
+        col += UnicodeWidthStr::width(g);
+    }
+    (offset, col as u16)
+}
+
+impl fmt::Debug for TerminalBuffer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TerminalBuffer")
+            .field("prefix", &self.prefix.as_str())
+            .field("buffer", &self.with_cursor_marker("|"))
+            .field("mark", &self.mark)
+            .finish()
+    }
+}
+
+impl From<TerminalBuffer<'_>> for String {
+    fn from(buf: TerminalBuffer<'_>) -> Self {
+        buf.buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLEAR_ALL: &str = "\x1b[2J";
+    const CLEAR_DOWN: &str = "\x1b[J";
+
+    fn output(bytes: &[u8]) -> String {
+        String::from_utf8_lossy(bytes).into_owned()
+    }
+
+    #[test]
+    fn start_below_existing_output() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.set_size((80, 24));
+        buf.start(&mut writer, (5, 10))?;
+        assert_eq!(11, buf.origin);
+
+        let out = output(&writer);
+        assert!(!out.contains(CLEAR_ALL));
+        assert!(out.starts_with("\r\n"));
+        assert!(out.contains("\x1b[12;1H\x1b[J> "));
+        Ok(())
+    }
+
+    #[test]
+    fn start_at_line_begin() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.set_size((80, 24));
+        buf.start(&mut writer, (0, 4))?;
+        assert_eq!(4, buf.origin);
+        assert!(!output(&writer).starts_with("\r\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn redraw_clears_prompt_rows() -> Result<()> {
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.set_size((80, 24));
+        buf.start(&mut Vec::new(), (0, 7))?;
+
+        let mut writer = Vec::new();
+        buf.write_char(&mut writer, 'a')?;
+        buf.clear_screen(&mut writer)?;
+        let out = output(&writer);
+        assert_eq!(1, out.matches(CLEAR_ALL).count());
+        assert_eq!(1, out.matches(CLEAR_DOWN).count());
+        assert!(out.starts_with("\x1b[8;3Ha\x1b[K\x1b[8;4H"));
+        assert!(out.ends_with("\x1b[1;1H\x1b[J> a\x1b[1;4H"));
+        Ok(())
+    }
+
+    #[test]
+    fn redraw_changes_only() -> Result<()> {
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.set_size((80, 24));
+        let mut writer = Vec::new();
+        buf.start(&mut writer, (0, 0))?;
+        buf.insert(&mut writer, &"x".repeat(60))?;
+
+        buf.move_left(&mut writer)?;
+        let mut writer = Vec::new();
+        buf.write_char(&mut writer, 'y')?;
+        let diff = output(&writer);
+        assert_eq!("\x1b[1;62Hyx\x1b[K\x1b[1;63H", diff);
+
+        let mut writer = Vec::new();
+        buf.clear_screen(&mut writer)?;
+        assert!(diff.len() * 3 < writer.len());
+
+        // Removed rows are cleared
+        buf.set_size((10, 24));
+        buf.redraw(&mut Vec::new())?;
+        let mut writer = Vec::new();
+        buf.refresh(&mut writer, "ab")?;
+        let out = output(&writer);
+        assert!(out.starts_with("\x1b[1;3Hab\x1b[K\x1b[2;1H\x1b[J"));
+        Ok(())
+    }
+
+    #[test]
+    fn redraw_wrapped_scrolls_origin() -> Result<()> {
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.set_size((10, 5));
+        buf.start(&mut Vec::new(), (0, 4))?;
+        let mut writer = Vec::new();
+        buf.insert(&mut writer, "0123")?;
+        assert!(!buf.is_wrapped());
+        buf.insert(&mut writer, "456789")?;
+        assert_eq!(2, buf.row_count());
+        assert!(buf.is_wrapped());
+        assert_eq!(3, buf.origin);
+        assert!(output(&writer).ends_with("\x1b[5;3H"));
+        Ok(())
+    }
+
+    #[test]
+    fn redraw_new_rows_from_last_row() -> Result<()> {
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.set_size((10, 24));
+        buf.start(&mut Vec::new(), (0, 23))?;
+        let mut writer = Vec::new();
+        buf.insert(&mut writer, &"x".repeat(25))?;
+        assert_eq!(3, buf.row_count());
+        assert_eq!(21, buf.origin);
+
+        // Every new row is written from the last terminal row
+        let out = output(&writer);
+        assert_eq!(2, out.matches("\x1b[24;1H\r\n").count());
+        assert!(!out.contains("\x1b[25;1H"));
+        assert!(out.ends_with("\x1b[24;8H"));
+        Ok(())
+    }
+
+    /// Writer that counts the number of flushes.
+    #[derive(Default)]
+    struct FlushCount {
+        bytes: Vec<u8>,
+        flushes: usize,
+    }
+
+    impl Write for FlushCount {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.bytes.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn defer_flush() -> Result<()> {
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.set_size((80, 24));
+        buf.set_defer_flush(true);
+        let mut writer = FlushCount::default();
+        buf.start(&mut writer, (0, 0))?;
+        for c in "the quick brown fox".chars().cycle().take(1000) {
+            buf.write_char(&mut writer, c)?;
+            buf.move_left(&mut writer)?;
+            buf.move_right(&mut writer)?;
+        }
+        buf.ring_bell(&mut writer)?;
+        assert_eq!(0, writer.flushes);
+        buf.finish(&mut writer)?;
+        assert_eq!(1, writer.flushes);
+        assert_eq!(1000, buf.buffer().len());
+
+        buf.set_defer_flush(false);
+        buf.write_char(&mut writer, 'a')?;
+        assert_eq!(2, writer.flushes);
+        Ok(())
+    }
+
+    #[test]
+    fn move_by_paragraph() -> Result<()> {
+        let mut buf = TerminalBuffer::new("> ", None);
+        let mut writer = Vec::new();
+        buf.insert(&mut writer, "one\ntwo\n\n  \nthree\n\nfour")?;
+        buf.move_to_previous_paragraph(&mut writer)?;
+        assert_eq!(
+            "one\ntwo\n\n  \nthree\n|\nfour",
+            buf.with_cursor_marker("|")
+        );
+        buf.move_to_previous_paragraph(&mut writer)?;
+        assert_eq!(
+            "one\ntwo\n\n|  \nthree\n\nfour",
+            buf.with_cursor_marker("|")
+        );
+        buf.move_to_previous_paragraph(&mut writer)?;
+        assert_eq!(
+            "|one\ntwo\n\n  \nthree\n\nfour",
+            buf.with_cursor_marker("|")
+        );
+
+        buf.move_right(&mut writer)?;
+        buf.move_to_next_paragraph(&mut writer)?;
+        assert_eq!(
+            "one\ntwo\n|\n  \nthree\n\nfour",
+            buf.with_cursor_marker("|")
+        );
+        buf.move_to_next_paragraph(&mut writer)?;
+        assert_eq!(
+            "one\ntwo\n\n  \nthree\n|\nfour",
+            buf.with_cursor_marker("|")
+        );
+        buf.move_to_next_paragraph(&mut writer)?;
+        assert_eq!(
+            "one\ntwo\n\n  \nthree\n\nfour|",
+            buf.with_cursor_marker("|")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sort_lines() -> Result<()> {
+        let mut buf = TerminalBuffer::new("> ", None);
+        let mut writer = Vec::new();
+        buf.insert(&mut writer, "pear\napple\nfig\napple")?;
+        buf.move_to_line_begin(&mut writer)?;
+        buf.move_right(&mut writer)?;
+        buf.sort_lines(&mut writer)?;
+        assert_eq!("a|pple\napple\nfig\npear", buf.with_cursor_marker("|"));
+        Ok(())
+    }
+
+    #[test]
+    fn dedup_lines() -> Result<()> {
+        let mut buf = TerminalBuffer::new("> ", None);
+        let mut writer = Vec::new();
+        buf.insert(&mut writer, "pear\napple\npear\nfig\napple\nfig")?;
+        buf.dedup_lines(&mut writer)?;
+        assert_eq!("pear\napple\nfig|", buf.with_cursor_marker("|"));
+        Ok(())
+    }
+
+    #[test]
+    fn toggle_line_prefix() -> Result<()> {
+        let mut buf = TerminalBuffer::new("> ", None);
+        let mut writer = Vec::new();
+        buf.insert(&mut writer, "let a = 1;\nlet b = 2;")?;
+        buf.move_left(&mut writer)?;
+        buf.toggle_line_prefix(&mut writer, "// ")?;
+        assert_eq!("let a = 1;\n// let b = 2|;", buf.with_cursor_marker("|"));
+        buf.toggle_line_prefix(&mut writer, "// ")?;
+        assert_eq!("let a = 1;\nlet b = 2|;", buf.with_cursor_marker("|"));
+
+        // Cursor inside the prefix moves to the line begin
+        buf.toggle_line_prefix(&mut writer, "// ")?;
+        buf.move_to_line_begin(&mut writer)?;
+        buf.move_right(&mut writer)?;
+        buf.toggle_line_prefix(&mut writer, "// ")?;
+        assert_eq!("let a = 1;\n|let b = 2;", buf.with_cursor_marker("|"));
+        Ok(())
+    }
+
+    #[test]
+    fn insert_lines() -> Result<()> {
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.set_size((80, 24));
+        buf.set_repeat_prefix(true);
+        buf.start(&mut Vec::new(), (0, 0))?;
+
+        let mut writer = Vec::new();
+        buf.insert_lines(&mut writer, "a\r\nb\nc")?;
+        assert_eq!(vec!["a", "b", "c"], buf.lines().collect::<Vec<_>>());
+        assert_eq!(5, buf.cursor());
+        let layout = buf.layout();
+        assert_eq!(vec!["> a", "> b", "> c"], layout.rows);
+        assert_eq!((3, 2), layout.cursor);
+        assert!(output(&writer).ends_with("\x1b[3;4H"));
+        Ok(())
+    }
+
+    #[test]
+    fn edit_graphemes() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.insert(&mut writer, "aé日b")?;
+        buf.move_left(&mut writer)?;
+        buf.erase_before(&mut writer, 1)?;
+        assert_eq!("aéb", buf.buffer());
+        buf.move_to_line_begin(&mut writer)?;
+        buf.erase_after(&mut writer, 2)?;
+        assert_eq!("b", buf.buffer());
+        Ok(())
+    }
+
+    #[test]
+    fn mask_wide_graphemes() -> Result<()> {
+        let mut buf = TerminalBuffer::new("> ", Some('*'));
+        buf.set_size((80, 24));
+        buf.start(&mut Vec::new(), (0, 0))?;
+
+        let mut writer = Vec::new();
+        buf.insert(&mut writer, "日本")?;
+        let out = output(&writer);
+        assert!(out.starts_with("\x1b[1;3H**\x1b[K"));
+        assert!(out.ends_with("\x1b[1;5H"));
+
+        let mut writer = Vec::new();
+        buf.move_left(&mut writer)?;
+        assert_eq!("\x1b[1;4H", output(&writer));
+
+        let mut writer = Vec::new();
+        buf.insert(&mut writer, "e\u{301}")?;
+        assert!(output(&writer).contains("\x1b[1;5H*\x1b[K"));
+        assert!(output(&writer).ends_with("\x1b[1;5H"));
+        assert_eq!("日e\u{301}本", buf.buffer());
+        Ok(())
+    }
+
+    #[test]
+    fn theme_segments() -> Result<()> {
+        use crossterm::style::Attributes;
+
+        let style = |color: Color, attributes: &[Attribute]| ContentStyle {
+            foreground_color: Some(color),
+            background_color: None,
+            attributes: Attributes::from(attributes),
+        };
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.set_theme(Theme {
+            prefix: style(Color::Green, &[]),
+            value: style(Color::Blue, &[Attribute::Bold]),
+            info: ContentStyle {
+                background_color: Some(Color::Grey),
+                ..Default::default()
+            },
+        });
+        buf.set_size((4, 24));
+        buf.insert(&mut Vec::new(), "abcd")?;
+
+        let green = SetForegroundColor(Color::Green).to_string();
+        let blue = format!(
+            "{}{}",
+            SetForegroundColor(Color::Blue),
+            SetAttribute(Attribute::Bold)
+        );
+        let reset = "\x1b[0m";
+        let rows = buf.layout().rows;
+        assert_eq!(format!("{}> {}{}ab{}", green, reset, blue, reset), rows[0]);
+        // The value style continues on the wrapped row
+        assert_eq!(format!("{}cd{}", blue, reset), rows[1]);
+        assert_eq!("> abcd", buf.visible_line().replace('\n', ""));
+
+        #[cfg(any(feature = "history", feature = "completion"))]
+        {
+            buf.set_info(vec!["info".to_string()]);
+            let grey = SetBackgroundColor(Color::Grey).to_string();
+            let rows = buf.layout().rows;
+            assert_eq!(format!("{}info{}", grey, reset), rows[2]);
+        }
+
+        // The color for a submitted line replaces the theme
+        buf.set_color(Some(Color::Red));
+        let red = SetForegroundColor(Color::Red).to_string();
+        let rows = buf.layout().rows;
+        assert_eq!(format!("{}> ab{}", red, reset), rows[0]);
+        Ok(())
+    }
+
+    #[test]
+    fn wrap_marker() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.set_size((10, 24));
+        buf.set_wrap_marker(Some('↩'));
+        buf.insert(&mut writer, "abcdefghijklmn")?;
+        let layout = buf.layout();
+        assert_eq!(vec!["> abcdefg↩", "hijklmn"], layout.rows);
+        assert_eq!((7, 1), layout.cursor);
+
+        // Wide graphemes that do not fit are padded before the marker
+        buf.refresh(&mut writer, "abcdef日本\nx")?;
+        assert_eq!(vec!["> abcdef ↩", "日本", "x"], buf.layout().rows);
+
+        // Filling the row moves the cursor to a new row
+        buf.refresh(&mut writer, "abcdefg")?;
+        let layout = buf.layout();
+        assert_eq!(vec!["> abcdefg↩", ""], layout.rows);
+        assert_eq!((0, 1), layout.cursor);
+        Ok(())
+    }
+
+    #[test]
+    fn counter() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.set_counter(Some(Counter {
+            maximum: Some(8),
+            columns: false,
+        }));
+        buf.insert(&mut writer, "日本")?;
+        assert_eq!(vec!["> 日本", "2/8"], buf.layout().rows);
+        buf.insert(&mut writer, "x")?;
+        assert_eq!("3/8", buf.layout().rows[1]);
+        buf.erase_before(&mut writer, 2)?;
+        assert_eq!("1/8", buf.layout().rows[1]);
+
+        // Display columns counted without a maximum
+        buf.insert(&mut writer, "語")?;
+        buf.set_counter(Some(Counter {
+            maximum: None,
+            columns: true,
+        }));
+        assert_eq!("4", buf.layout().rows[1]);
+
+        // Not displayed while the value is hidden
+        buf.set_hidden(true);
+        assert_eq!(1, buf.layout().rows.len());
+        Ok(())
+    }
+
+    #[test]
+    fn border_frame() -> Result<()> {
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.set_border(Some(Default::default()));
+        buf.set_size((10, 24));
+        let mut writer = Vec::new();
+        buf.start(&mut writer, (0, 0))?;
+        let mut writer = Vec::new();
+        buf.insert(&mut writer, "abc")?;
+        let layout = buf.layout();
+        assert_eq!(vec!["┌────────┐", "│> abc   │", "└────────┘"], layout.rows);
+        // The cursor is after the left border
+        assert_eq!((6, 1), layout.cursor);
+        assert!(output(&writer).ends_with("\x1b[2;7H"));
+        assert!(!buf.is_wrapped());
+
+        // The value wraps inside the border
+        buf.insert(&mut writer, "defghi")?;
+        let layout = buf.layout();
+        assert_eq!(
+            vec!["┌────────┐", "│> abcdef│", "│ghi     │", "└────────┘"],
+            layout.rows
+        );
+        assert_eq!((4, 2), layout.cursor);
+        assert!(buf.is_wrapped());
+
+        buf.set_border(Some(Border {
+            vertical: '|',
+            style: ContentStyle {
+                foreground_color: Some(Color::Cyan),
+                ..Default::default()
+            },
+            ..Default::default()
+        }));
+        let cyan = SetForegroundColor(Color::Cyan).to_string();
+        let rows = buf.layout().rows;
+        assert_eq!(
+            format!("{}|\x1b[0m> abcdef{}|\x1b[0m", cyan, cyan),
+            rows[1]
+        );
+        assert_eq!(format!("{}┌────────┐\x1b[0m", cyan), rows[0]);
+        Ok(())
+    }
+
+    #[test]
+    fn visible_line() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.set_size((8, 24));
+        buf.insert(&mut writer, "foo\tbar")?;
+        assert_eq!("> foo   \nbar", buf.visible_line());
+
+        buf.set_echo(Some('*'));
+        assert_eq!("> ******\n*", buf.visible_line());
+        Ok(())
+    }
+
+    #[cfg(feature = "completion")]
+    #[test]
+    fn styled_info() -> Result<()> {
+        use crate::completion::{Columns, Completion};
+        let candidates = vec![
+            Completion::from("status").description("Show the working tree"),
+            Completion::from("stash"),
+        ];
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.set_size((16, 24));
+        buf.set_info(Columns::new(&candidates, 16, 0).lines(&candidates, None));
+
+        // Style codes in the info lines do not occupy columns
+        let rows = buf.layout().rows;
+        assert_eq!(3, rows.len());
+        assert!(rows[1].contains("\x1b[2mShow…\x1b[22m"));
+        assert_eq!("  status  Show…", strip_styles(&rows[1]));
+        Ok(())
+    }
+
+    #[cfg(feature = "history")]
+    #[test]
+    fn visible_line_without_styles() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.insert(&mut writer, "foo bar")?;
+        buf.set_highlight(Some(0..3));
+        buf.set_status(Some("(search) ".to_string()));
+        buf.set_info(vec!["info".to_string()]);
+        assert!(buf.layout().rows[0].contains('\x1b'));
+        assert_eq!("(search) foo bar", buf.visible_line());
+        Ok(())
+    }
+
+    #[cfg(feature = "history")]
+    #[test]
+    fn highlight_wraps() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.set_size((10, 24));
+        buf.insert(&mut writer, "abcdefghij")?;
+        buf.set_highlight(Some(5..10));
+        let rows = buf.layout().rows;
+        assert_eq!("> abcde\x1b[4mfgh\x1b[24m", rows[0]);
+        assert_eq!("\x1b[4mij\x1b[24m", rows[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn mark_follows_edits() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.insert(&mut writer, "foo bar")?;
+        buf.set_mark();
+        buf.move_to_line_begin(&mut writer)?;
+        buf.insert(&mut writer, "日")?;
+        assert_eq!(Some(3..10), buf.region());
+        buf.erase_before(&mut writer, 1)?;
+        assert_eq!(Some(0..7), buf.region());
+        assert_eq!("foo bar", buf.erase_region(&mut writer)?);
+        assert_eq!(None, buf.region());
+        Ok(())
+    }
+
+    #[test]
+    fn cursor_marker() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        assert_eq!("|", buf.with_cursor_marker("|"));
+        buf.insert(&mut writer, "foo日bar")?;
+        assert_eq!("foo日bar|", buf.with_cursor_marker("|"));
+        for _ in 0..3 {
+            buf.move_left(&mut writer)?;
+        }
+        assert_eq!("foo日|bar", buf.with_cursor_marker("|"));
+        buf.move_left(&mut writer)?;
+        assert_eq!("foo|日bar", buf.with_cursor_marker("|"));
+        buf.move_to_line_begin(&mut writer)?;
+        assert_eq!("|foo日bar", buf.with_cursor_marker("|"));
+        assert!(format!("{:?}", buf).contains(r#"buffer: "|foo日bar""#));
+        Ok(())
+    }
+
+    #[test]
+    fn erase_word() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.insert(&mut writer, "foo   bar  ")?;
+        assert_eq!("bar  ", buf.erase_word_before(&mut writer)?);
+        assert_eq!("foo   ", buf.buffer());
+        assert_eq!("foo   ", buf.erase_word_before(&mut writer)?);
+        assert_eq!("", buf.buffer());
+        assert_eq!("", buf.erase_word_before(&mut writer)?);
+        Ok(())
+    }
+
+    #[test]
+    fn erase_word_stop_at_whitespace() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.set_stop_at_whitespace(true);
+        buf.insert(&mut writer, "foo   bar  ")?;
+        assert_eq!("  ", buf.erase_word_before(&mut writer)?);
+        assert_eq!("bar", buf.erase_word_before(&mut writer)?);
+        assert_eq!("   ", buf.erase_word_before(&mut writer)?);
+        assert_eq!("foo", buf.erase_word_before(&mut writer)?);
+        assert_eq!("", buf.buffer());
+        Ok(())
+    }
+
+    #[test]
+    fn replace_horizontal_space() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.insert(&mut writer, "foo \t  bar")?;
+        for _ in 0..5 {
+            buf.move_left(&mut writer)?;
+        }
+        buf.replace_horizontal_space(&mut writer, "")?;
+        assert_eq!("foo|bar", buf.with_cursor_marker("|"));
+
+        // Nothing to replace away from whitespace
+        buf.replace_horizontal_space(&mut writer, "")?;
+        assert_eq!("foo|bar", buf.with_cursor_marker("|"));
+        buf.replace_horizontal_space(&mut writer, " ")?;
+        assert_eq!("foo |bar", buf.with_cursor_marker("|"));
+        Ok(())
+    }
+
+    #[test]
+    fn wrap_region() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.insert(&mut writer, "echo foo")?;
+        for _ in 0..3 {
+            buf.move_left(&mut writer)?;
+        }
+        buf.set_mark();
+        buf.move_to_line_end(&mut writer)?;
+        buf.wrap_region(&mut writer, '"', '"')?;
+        assert_eq!("echo \"foo\"|", buf.with_cursor_marker("|"));
+        assert_eq!(None, buf.region());
+
+        buf.insert(&mut writer, " ")?;
+        buf.wrap_region(&mut writer, '(', ')')?;
+        assert_eq!("echo \"foo\" (|)", buf.with_cursor_marker("|"));
+        Ok(())
+    }
+
+    #[test]
+    fn erase_whitespace_word() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.insert(&mut writer, "ls foo/bar  ")?;
+        assert_eq!("foo/bar  ", buf.erase_whitespace_word_before(&mut writer)?);
+        assert_eq!("ls ", buf.buffer());
+        assert_eq!("ls ", buf.erase_whitespace_word_before(&mut writer)?);
+        assert_eq!("", buf.erase_whitespace_word_before(&mut writer)?);
+        Ok(())
+    }
+
+    #[test]
+    fn transpose_forward() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.insert(&mut writer, "a日e\u{301}")?;
+        buf.move_to_line_begin(&mut writer)?;
+        buf.move_right(&mut writer)?;
+        assert!(buf.transpose_forward(&mut writer)?);
+        assert_eq!("ae\u{301}|日", buf.with_cursor_marker("|"));
+
+        // Nothing after the last grapheme
+        assert!(!buf.transpose_forward(&mut writer)?);
+        assert_eq!("ae\u{301}|日", buf.with_cursor_marker("|"));
+        buf.move_to_line_end(&mut writer)?;
+        assert!(!buf.transpose_forward(&mut writer)?);
+        Ok(())
+    }
+
+    #[test]
+    fn replace_range() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.insert(&mut writer, "abcdef")?;
+        buf.replace_range(&mut writer, 2, 4, "XYZW")?;
+        assert_eq!("abXYZW|ef", buf.with_cursor_marker("|"));
+        buf.replace_range(&mut writer, 1, 7, "-")?;
+        assert_eq!("a-|f", buf.with_cursor_marker("|"));
+
+        // Wide graphemes partially in the range are replaced
+        buf.refresh(&mut writer, "日本語")?;
+        buf.replace_range(&mut writer, 1, 3, "x")?;
+        assert_eq!("x|語", buf.with_cursor_marker("|"));
+        buf.refresh(&mut writer, "日本語")?;
+        buf.replace_range(&mut writer, 2, 4, "ab")?;
+        assert_eq!("日ab|語", buf.with_cursor_marker("|"));
+
+        // An empty range inserts and the end of the line appends
+        buf.replace_range(&mut writer, 2, 2, "+")?;
+        assert_eq!("日+|ab語", buf.with_cursor_marker("|"));
+        buf.replace_range(&mut writer, 20, 30, "!")?;
+        assert_eq!("日+ab語!|", buf.with_cursor_marker("|"));
+
+        // Tabs expand to the tab stop after the prefix
+        buf.refresh(&mut writer, "a\tbc")?;
+        assert_eq!(8, buf.cursor_column());
+        buf.replace_range(&mut writer, 6, 7, "B")?;
+        assert_eq!("a\tB|c", buf.with_cursor_marker("|"));
+        assert_eq!(7, buf.cursor_column());
+        Ok(())
+    }
+
+    #[test]
+    fn erase_returns_text() -> Result<()> {
+        let mut writer = Vec::new();
+        let mut buf = TerminalBuffer::new("> ", None);
+        buf.insert(&mut writer, "日本 foo")?;
+        buf.move_to_line_begin(&mut writer)?;
+        buf.move_right(&mut writer)?;
+        assert_eq!("日", buf.erase_word_before(&mut writer)?);
+        assert_eq!("本 foo", buf.erase_to_line_end(&mut writer)?);
+        assert_eq!("", buf.buffer());
+        Ok(())
     }
 }
@@ -0,0 +1,94 @@
+//! Format timestamps using a subset of the `strftime` specifiers.
+//!
+//! Times are always formatted in UTC.
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Format used when no format is configured or the
+/// configured format is invalid.
+pub(crate) const DEFAULT_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Format a time, an unknown specifier is an error.
+///
+/// Supported specifiers are `%Y`, `%m`, `%d`, `%H`, `%M`, `%S`,
+/// `%s` for the seconds since the epoch and `%%`.
+pub(crate) fn format(time: SystemTime, format: &str) -> Option<String> {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let seconds = secs % 86400;
+
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let _ = match chars.next()? {
+            'Y' => write!(out, "{:04}", year),
+            'm' => write!(out, "{:02}", month),
+            'd' => write!(out, "{:02}", day),
+            'H' => write!(out, "{:02}", seconds / 3600),
+            'M' => write!(out, "{:02}", seconds / 60 % 60),
+            'S' => write!(out, "{:02}", seconds % 60),
+            's' => write!(out, "{}", secs),
+            '%' => write!(out, "%"),
+            _ => return None,
+        };
+    }
+    Some(out)
+}
+
+/// Format a time falling back to the default format when
+/// the format is invalid.
+pub(crate) fn format_or_default(time: SystemTime, format: &str) -> String {
+    self::format(time, format)
+        .or_else(|| self::format(time, DEFAULT_FORMAT))
+        .unwrap_or_default()
+}
+
+/// Convert days since the epoch to a year, month and day.
+///
+/// See http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn format_timestamp() {
+        // 2024-02-29 13:05:09 UTC
+        let time = UNIX_EPOCH + Duration::from_secs(1709211909);
+        assert_eq!(
+            Some("2024-02-29 13:05:09".to_string()),
+            format(time, DEFAULT_FORMAT)
+        );
+        assert_eq!(
+            Some("[29/02/2024 100%] 1709211909".to_string()),
+            format(time, "[%d/%m/%Y 100%%] %s")
+        );
+        assert_eq!(None, format(time, "%Q"));
+        assert_eq!(None, format(time, "trailing %"));
+        assert_eq!("2024-02-29 13:05:09", format_or_default(time, "%Q"));
+        assert_eq!(
+            Some("1970-01-01".to_string()),
+            format(UNIX_EPOCH, "%Y-%m-%d")
+        );
+    }
+}